@@ -0,0 +1,130 @@
+//! Bump allocation logic shared by the on-chain `CustomizeBumpAllocator` and the
+//! native `BumpAllocator` / `ThreadSafeBumpAllocator`.
+//!
+//! [`Bump`] does the alignment and bounds math over an address range and hands out
+//! offsets into it. It never touches the memory itself, so the same code runs on the
+//! host in tests and on-chain over the program heap.
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+mod position;
+
+pub use position::{BumpPosition, HeapWord};
+
+/// Why an allocation could not be served, i.e. which limit it hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocError {
+    /// Aligning or adding the size ran past the end of the address space.
+    Overflow,
+    /// The allocation does not fit in the remaining capacity.
+    OutOfMemory,
+    /// The requested alignment is not a non-zero power of two.
+    InvalidAlignment,
+}
+
+impl core::fmt::Display for AllocError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AllocError::Overflow => f.write_str("allocation overflows the address space"),
+            AllocError::OutOfMemory => f.write_str("bump allocator out of memory"),
+            AllocError::InvalidAlignment => f.write_str("alignment is not a power of two"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
+
+/// Round `address` up to a multiple of `align`, or `None` if that overflows or
+/// `align` is not a non-zero power of two.
+#[inline]
+pub fn align_up(address: usize, align: usize) -> Option<usize> {
+    checked_align_up(address, align).ok()
+}
+
+/// Round `address` up to a multiple of `align`, reporting why that is impossible.
+#[inline]
+pub fn checked_align_up(address: usize, align: usize) -> Result<usize, AllocError> {
+    check_align(align)?;
+    let mask = align - 1;
+    Ok(address.checked_add(mask).ok_or(AllocError::Overflow)? & !mask)
+}
+
+/// Reject alignments that are not a non-zero power of two.
+#[inline]
+pub fn check_align(align: usize) -> Result<(), AllocError> {
+    if align.is_power_of_two() {
+        Ok(())
+    } else {
+        Err(AllocError::InvalidAlignment)
+    }
+}
+
+/// Bump allocator over `capacity` bytes starting at address `start`.
+///
+/// Alignment is applied to the absolute address `start + offset`, so a block is
+/// aligned even when `start` itself is not.
+pub struct Bump<P> {
+    start: usize,
+    capacity: usize,
+    position: P,
+}
+
+impl<P> Bump<P> {
+    pub const fn new(start: usize, capacity: usize, position: P) -> Self {
+        Bump {
+            start,
+            capacity,
+            position,
+        }
+    }
+
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline]
+    pub fn position(&self) -> &P {
+        &self.position
+    }
+}
+
+impl<P: BumpPosition> Bump<P> {
+    /// Reserve `size` bytes aligned to `align` and return their offset from `start`.
+    /// `align` must be a non-zero power of two, or `InvalidAlignment` is returned.
+    #[inline]
+    pub fn alloc(&self, size: usize, align: usize) -> Result<usize, AllocError> {
+        let mut block = 0;
+        self.position.try_update(|offset| {
+            let address = self.start.checked_add(offset).ok_or(AllocError::Overflow)?;
+            let aligned = checked_align_up(address, align)?;
+            let end = aligned.checked_add(size).ok_or(AllocError::Overflow)?;
+            if end - self.start > self.capacity {
+                return Err(AllocError::OutOfMemory);
+            }
+            block = aligned - self.start;
+            Ok(end - self.start)
+        })?;
+        Ok(block)
+    }
+
+    /// Forget every allocation and start again from offset 0.
+    #[inline]
+    pub fn reset(&self) {
+        self.position.set(0);
+    }
+
+    /// Bytes consumed so far, alignment padding included.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.position.get()
+    }
+}
@@ -0,0 +1,164 @@
+use core::cell::Cell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Storage for the bump position, i.e. the offset of the first free byte.
+///
+/// The allocation math in [`Bump`](crate::Bump) is the same everywhere; what differs
+/// between allocators is only where the offset lives and how it is updated.
+pub trait BumpPosition {
+    /// Current offset.
+    fn get(&self) -> usize;
+
+    /// Overwrite the offset.
+    fn set(&self, offset: usize);
+
+    /// Replace the offset with `f(offset)`, as a single step with respect to other
+    /// updates. Returns the previous offset, or the error from `f`, in which case
+    /// the offset is left untouched. `f` may be called more than once.
+    fn try_update<E>(&self, f: impl FnMut(usize) -> Result<usize, E>) -> Result<usize, E>;
+}
+
+/// Single-threaded position, used by the native `BumpAllocator`.
+impl BumpPosition for Cell<usize> {
+    #[inline]
+    fn get(&self) -> usize {
+        Cell::get(self)
+    }
+
+    #[inline]
+    fn set(&self, offset: usize) {
+        Cell::set(self, offset)
+    }
+
+    #[inline]
+    fn try_update<E>(&self, mut f: impl FnMut(usize) -> Result<usize, E>) -> Result<usize, E> {
+        let current = Cell::get(self);
+        Cell::set(self, f(current)?);
+        Ok(current)
+    }
+}
+
+macro_rules! atomic_position {
+    ($(#[$attr:meta])* $atomic:ty) => {
+        $(#[$attr])*
+        impl BumpPosition for $atomic {
+            #[inline]
+            fn get(&self) -> usize {
+                self.load(Ordering::Acquire)
+            }
+
+            #[inline]
+            fn set(&self, offset: usize) {
+                self.store(offset, Ordering::Release)
+            }
+
+            #[inline]
+            fn try_update<E>(
+                &self,
+                mut f: impl FnMut(usize) -> Result<usize, E>,
+            ) -> Result<usize, E> {
+                let mut current = self.load(Ordering::Relaxed);
+                loop {
+                    let next = f(current)?;
+                    match self.compare_exchange_weak(
+                        current,
+                        next,
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(previous) => return Ok(previous),
+                        Err(actual) => current = actual,
+                    }
+                }
+            }
+        }
+    };
+}
+
+atomic_position!(
+    /// Lock-free position updated with a compare-and-swap loop.
+    AtomicUsize
+);
+
+#[cfg(loom)]
+atomic_position!(
+    /// The same position under the loom model checker, so the native allocators can
+    /// be tested with every interleaving of their atomic operations.
+    loom::sync::atomic::AtomicUsize
+);
+
+/// Position guarded by a lock, used by the native `ThreadSafeBumpAllocator`.
+#[cfg(feature = "std")]
+impl BumpPosition for std::sync::Mutex<usize> {
+    fn get(&self) -> usize {
+        *self
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn set(&self, offset: usize) {
+        *self
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = offset;
+    }
+
+    fn try_update<E>(&self, mut f: impl FnMut(usize) -> Result<usize, E>) -> Result<usize, E> {
+        let mut current = self
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let previous = *current;
+        *current = f(previous)?;
+        Ok(previous)
+    }
+}
+
+/// Position stored in a word of raw memory, as the on-chain allocator keeps it in
+/// the first word of the program heap.
+///
+/// The word holds the absolute address of the next free byte, the encoding Solana's
+/// default allocator also uses for that word, and 0 means `base`. The heap starts
+/// zeroed, so the first allocation sees offset 0. `HeapWord` converts between that
+/// address and the offset from `base` that [`Bump`](crate::Bump) works with.
+#[derive(Debug, Clone, Copy)]
+pub struct HeapWord {
+    word: *mut usize,
+    base: usize,
+}
+
+impl HeapWord {
+    /// Position at `word`, as an offset from `base`, which should be the `start` of
+    /// the [`Bump`](crate::Bump) it is used with.
+    ///
+    /// # Safety
+    ///
+    /// `word` must be valid for reads and writes of an aligned `usize` for as long as
+    /// the `HeapWord` is used, and nothing else may access it concurrently.
+    #[inline]
+    pub const unsafe fn new(word: *mut usize, base: usize) -> Self {
+        HeapWord { word, base }
+    }
+}
+
+impl BumpPosition for HeapWord {
+    /// An address below `base` comes out as an offset so large that `Bump` rejects
+    /// every allocation.
+    #[inline]
+    fn get(&self) -> usize {
+        match unsafe { *self.word } {
+            0 => 0,
+            address => address.wrapping_sub(self.base),
+        }
+    }
+
+    #[inline]
+    fn set(&self, offset: usize) {
+        unsafe { *self.word = self.base.wrapping_add(offset) }
+    }
+
+    #[inline]
+    fn try_update<E>(&self, mut f: impl FnMut(usize) -> Result<usize, E>) -> Result<usize, E> {
+        let current = self.get();
+        self.set(f(current)?);
+        Ok(current)
+    }
+}
@@ -0,0 +1,84 @@
+//! Property tests for the alignment math over the whole `usize` range.
+
+use bump_core::{align_up, check_align, checked_align_up, AllocError, Bump};
+use proptest::prelude::*;
+use std::cell::Cell;
+
+/// Any power of two that fits in a `usize`.
+fn power_of_two() -> impl Strategy<Value = usize> {
+    (0..usize::BITS).prop_map(|shift| 1usize << shift)
+}
+
+/// Any `usize` that is not a power of two, zero included.
+fn not_power_of_two() -> impl Strategy<Value = usize> {
+    any::<usize>().prop_filter("power of two", |align| !align.is_power_of_two())
+}
+
+/// Addresses spread over the whole range, with the edges where rounding overflows
+/// drawn often.
+fn address() -> impl Strategy<Value = usize> {
+    prop_oneof![
+        any::<usize>(),
+        (0..4096usize).prop_map(|below| usize::MAX - below),
+        0..4096usize,
+    ]
+}
+
+proptest! {
+    #[test]
+    fn rounds_up_to_the_next_multiple(address in address(), align in power_of_two()) {
+        match checked_align_up(address, align) {
+            Ok(aligned) => {
+                prop_assert_eq!(aligned % align, 0);
+                prop_assert!(aligned >= address);
+                prop_assert!(aligned - address < align);
+            }
+            Err(error) => {
+                prop_assert_eq!(error, AllocError::Overflow);
+                // Only when the next multiple is past `usize::MAX`
+                prop_assert!(address > usize::MAX - (align - 1));
+            }
+        }
+        prop_assert_eq!(align_up(address, align), checked_align_up(address, align).ok());
+    }
+
+    #[test]
+    fn aligned_addresses_are_unchanged(multiple in any::<usize>(), align in power_of_two()) {
+        let address = multiple & !(align - 1);
+        prop_assert_eq!(checked_align_up(address, align), Ok(address));
+    }
+
+    #[test]
+    fn rejects_alignments_that_are_not_powers_of_two(
+        address in address(),
+        align in not_power_of_two(),
+    ) {
+        prop_assert_eq!(check_align(align), Err(AllocError::InvalidAlignment));
+        prop_assert_eq!(checked_align_up(address, align), Err(AllocError::InvalidAlignment));
+        prop_assert_eq!(align_up(address, align), None);
+    }
+
+    #[test]
+    fn bump_rejects_invalid_alignment_without_moving(
+        size in 0..64usize,
+        align in not_power_of_two(),
+    ) {
+        let bump = Bump::new(0x1000, 256, Cell::new(0));
+        bump.alloc(1, 1).unwrap();
+        prop_assert_eq!(bump.alloc(size, align), Err(AllocError::InvalidAlignment));
+        prop_assert_eq!(bump.used(), 1);
+    }
+
+    #[test]
+    fn bump_blocks_are_aligned_or_rejected(
+        start in address(),
+        size in any::<usize>(),
+        align in power_of_two(),
+    ) {
+        let bump = Bump::new(start, usize::MAX - start, Cell::new(0));
+        if let Ok(offset) = bump.alloc(size, align) {
+            prop_assert_eq!((start + offset) % align, 0);
+            prop_assert_eq!(bump.used(), offset + size);
+        }
+    }
+}
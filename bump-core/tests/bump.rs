@@ -0,0 +1,146 @@
+//! Every allocator in the workspace is a `Bump` over a different `BumpPosition`:
+//! `HeapWord` on-chain, `Cell<usize>` for the native `BumpAllocator` and
+//! `Mutex<usize>` for `ThreadSafeBumpAllocator`. Each check runs against all of them.
+
+use bump_core::{align_up, AllocError, Bump, BumpPosition, HeapWord};
+use std::cell::Cell;
+use std::sync::atomic::AtomicUsize;
+
+const START: usize = 0x1000;
+const CAPACITY: usize = 256;
+
+fn check_all(check: impl Fn(&dyn Fn(usize, usize) -> Box<dyn Allocator>)) {
+    check(&|start, capacity| Box::new(Bump::new(start, capacity, Cell::new(0))));
+    check(&|start, capacity| Box::new(Bump::new(start, capacity, AtomicUsize::new(0))));
+    #[cfg(feature = "std")]
+    check(&|start, capacity| Box::new(Bump::new(start, capacity, std::sync::Mutex::new(0))));
+    check(&|start, capacity| {
+        let word = Box::leak(Box::new(0usize));
+        let position = unsafe { HeapWord::new(word, start) };
+        Box::new(Bump::new(start, capacity, position))
+    });
+}
+
+/// Object-safe view of `Bump<P>` so the checks can be written once.
+trait Allocator {
+    fn alloc(&self, size: usize, align: usize) -> Result<usize, AllocError>;
+    fn reset(&self);
+    fn used(&self) -> usize;
+}
+
+impl<P: BumpPosition> Allocator for Bump<P> {
+    fn alloc(&self, size: usize, align: usize) -> Result<usize, AllocError> {
+        Bump::alloc(self, size, align)
+    }
+
+    fn reset(&self) {
+        Bump::reset(self)
+    }
+
+    fn used(&self) -> usize {
+        Bump::used(self)
+    }
+}
+
+#[test]
+fn align_up_rounds_to_multiple() {
+    assert_eq!(align_up(0, 8), Some(0));
+    assert_eq!(align_up(1, 8), Some(8));
+    assert_eq!(align_up(8, 8), Some(8));
+    assert_eq!(align_up(9, 1), Some(9));
+    assert_eq!(align_up(usize::MAX - 6, 8), None);
+    assert_eq!(align_up(usize::MAX, 1), Some(usize::MAX));
+}
+
+#[test]
+fn allocations_are_aligned_and_disjoint() {
+    check_all(|new| {
+        let bump = new(START, CAPACITY);
+        assert_eq!(bump.alloc(1, 1), Ok(0));
+        assert_eq!(bump.alloc(8, 8), Ok(8));
+        assert_eq!(bump.alloc(2, 2), Ok(16));
+        assert_eq!(bump.alloc(16, 32), Ok(32));
+        assert_eq!(bump.used(), 48);
+    });
+}
+
+#[test]
+fn alignment_applies_to_the_absolute_address() {
+    check_all(|new| {
+        let bump = new(START + 3, CAPACITY);
+        assert_eq!(bump.alloc(1, 1), Ok(0));
+        // START + 3 + 5 is the next multiple of 8
+        assert_eq!(bump.alloc(8, 8), Ok(5));
+        assert_eq!((START + 3 + 5) % 8, 0);
+    });
+}
+
+#[test]
+fn exact_fit_succeeds_and_overflowing_capacity_is_out_of_memory() {
+    check_all(|new| {
+        let bump = new(START, CAPACITY);
+        assert_eq!(bump.alloc(CAPACITY - 1, 1), Ok(0));
+        assert_eq!(bump.alloc(2, 1), Err(AllocError::OutOfMemory));
+        assert_eq!(bump.used(), CAPACITY - 1);
+        assert_eq!(bump.alloc(1, 1), Ok(CAPACITY - 1));
+        assert_eq!(bump.alloc(0, 1), Ok(CAPACITY));
+        assert_eq!(bump.alloc(0, 512), Err(AllocError::OutOfMemory));
+    });
+}
+
+#[test]
+fn address_space_overflow_is_reported_without_moving() {
+    check_all(|new| {
+        let bump = new(usize::MAX - 16, 16);
+        assert_eq!(bump.alloc(usize::MAX, 1), Err(AllocError::Overflow));
+        assert_eq!(bump.alloc(1, 1 << 20), Err(AllocError::Overflow));
+        assert_eq!(bump.used(), 0);
+        assert_eq!(bump.alloc(16, 1), Ok(0));
+    });
+}
+
+#[test]
+fn reset_starts_over() {
+    check_all(|new| {
+        let bump = new(START, CAPACITY);
+        bump.alloc(CAPACITY, 1).unwrap();
+        assert_eq!(bump.alloc(1, 1), Err(AllocError::OutOfMemory));
+        bump.reset();
+        assert_eq!(bump.used(), 0);
+        assert_eq!(bump.alloc(1, 1), Ok(0));
+    });
+}
+
+fn check_concurrent_allocations_are_disjoint<P: BumpPosition + Sync>(position: P) {
+    let threads = 8;
+    let per_thread = 1_000;
+    let bump = Bump::new(START, threads * per_thread * 8, position);
+
+    let mut offsets: Vec<usize> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    (0..per_thread)
+                        .map(|_| bump.alloc(8, 8).unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    offsets.sort_unstable();
+    offsets.dedup();
+    assert_eq!(offsets.len(), threads * per_thread);
+    assert_eq!(bump.alloc(1, 1), Err(AllocError::OutOfMemory));
+}
+
+#[test]
+fn concurrent_allocations_are_disjoint() {
+    check_concurrent_allocations_are_disjoint(AtomicUsize::new(0));
+    #[cfg(feature = "std")]
+    check_concurrent_allocations_are_disjoint(std::sync::Mutex::new(0));
+}
@@ -1,34 +1,47 @@
 use anchor_lang::solana_program::entrypoint::HEAP_START_ADDRESS;
+use bump_core::{Bump, HeapWord};
 use std::{alloc::Layout, mem::size_of, ptr::null_mut};
 
 /// Length of the memory region used for program heap.
 pub const HEAP_LENGTH: usize = 8 * 32 * 1024; // 256 KB
 
-pub struct CustomizeBumpAllocator;
+/// Bump allocator over the heap region `[start, start + len)`.
+///
+/// The first word of the region holds the address of the next free byte, or 0 before
+/// the first allocation, so `start` must be aligned for `usize` and the word must be
+/// zero to begin with.
+/// On-chain the region is the program heap (see [`PROGRAM_HEAP`]); on the host it
+/// can be any zeroed buffer, which is how the alignment math is tested.
+pub struct CustomizeBumpAllocator {
+    pub start: usize,
+    pub len: usize,
+}
+
+/// Allocator over the heap the runtime maps at `HEAP_START_ADDRESS`.
+pub const PROGRAM_HEAP: CustomizeBumpAllocator =
+    CustomizeBumpAllocator::new(HEAP_START_ADDRESS as usize, HEAP_LENGTH);
+
+impl CustomizeBumpAllocator {
+    pub const fn new(start: usize, len: usize) -> Self {
+        CustomizeBumpAllocator { start, len }
+    }
+}
 
 unsafe impl std::alloc::GlobalAlloc for CustomizeBumpAllocator {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        const POS_PTR: *mut usize = HEAP_START_ADDRESS as *mut usize;
-        const TOP_ADDRESS: usize = HEAP_START_ADDRESS as usize + HEAP_LENGTH;
-        const BOTTOM_ADDRESS: usize = HEAP_START_ADDRESS as usize + size_of::<*mut u8>();
-
-        let mut pos = *POS_PTR;
-        if pos == 0 {
-            // First time, set starting position to bottom address
-            pos = BOTTOM_ADDRESS;
+        // The first word holds the address of the next free byte, 0 meaning the bottom
+        let bottom_address = self.start + size_of::<*mut u8>();
+        let bump = Bump::new(
+            bottom_address,
+            self.len.saturating_sub(size_of::<*mut u8>()),
+            HeapWord::new(self.start as *mut usize, bottom_address),
+        );
+
+        match bump.alloc(layout.size(), layout.align()) {
+            Ok(offset) => (bottom_address + offset) as *mut u8,
+            Err(_) => null_mut(),
         }
-
-        // Align the position upwards
-        pos = (pos + layout.align() - 1) & !(layout.align() - 1);
-        let next_pos = pos.saturating_add(layout.size());
-
-        if next_pos > TOP_ADDRESS {
-            return null_mut();
-        }
-
-        *POS_PTR = next_pos;
-        pos as *mut u8
     }
 
     #[inline]
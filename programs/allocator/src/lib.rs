@@ -3,7 +3,7 @@ use std::alloc::{GlobalAlloc, Layout};
 
 pub mod customize_allocator;
 
-pub use crate::customize_allocator::CustomizeBumpAllocator;
+pub use crate::customize_allocator::{CustomizeBumpAllocator, PROGRAM_HEAP};
 declare_id!("6atnv2uSSvZ8byveP2xeYfwGUDwRDcXBqtRKrphGXRXR");
 
 #[program]
@@ -22,7 +22,7 @@ pub mod allocator {
         msg!("Bump Allocate: Allocating {} bytes", size);
         unsafe {
             let layout = Layout::from_size_align(size as usize, 8).unwrap();
-            let ptr = CustomizeBumpAllocator.alloc(layout);
+            let ptr = PROGRAM_HEAP.alloc(layout);
             if ptr.is_null() {
                 msg!("Bump Allocation Failed.");
                 return Err(ErrorCode::AllocationFailed.into());
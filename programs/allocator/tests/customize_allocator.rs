@@ -0,0 +1,128 @@
+use allocator::CustomizeBumpAllocator;
+use proptest::prelude::*;
+use std::alloc::{GlobalAlloc, Layout};
+use std::mem::size_of;
+
+/// Zeroed host buffer standing in for the program heap.
+struct HostHeap {
+    words: Vec<usize>,
+}
+
+impl HostHeap {
+    fn new(len: usize) -> Self {
+        HostHeap {
+            words: vec![0; len.div_ceil(size_of::<usize>())],
+        }
+    }
+
+    fn allocator(&mut self, len: usize) -> CustomizeBumpAllocator {
+        CustomizeBumpAllocator::new(self.words.as_mut_ptr() as usize, len)
+    }
+
+    /// Address of the next free byte, or 0 before the first allocation.
+    fn position(&self) -> usize {
+        self.words[0]
+    }
+
+    fn set_position(&mut self, pos: usize) {
+        self.words[0] = pos;
+    }
+}
+
+fn layout_strategy() -> impl Strategy<Value = Layout> {
+    (0usize..=1024, 0u32..=12)
+        .prop_map(|(size, align_shift)| Layout::from_size_align(size, 1 << align_shift).unwrap())
+}
+
+proptest! {
+    #[test]
+    fn allocations_are_aligned_disjoint_and_in_bounds(
+        len in size_of::<usize>()..16 * 1024,
+        layouts in prop::collection::vec(layout_strategy(), 1..64),
+    ) {
+        let mut heap = HostHeap::new(len);
+        let allocator = heap.allocator(len);
+        let bottom = allocator.start + size_of::<usize>();
+        let top = allocator.start + len;
+        let mut previous_end = bottom;
+
+        for layout in layouts {
+            let pos_before = heap.position();
+            let ptr = unsafe { allocator.alloc(layout) } as usize;
+
+            if ptr == 0 {
+                // A failed allocation must leave the position untouched and must really not fit
+                prop_assert_eq!(heap.position(), pos_before);
+                let aligned = (previous_end + layout.align() - 1) & !(layout.align() - 1);
+                prop_assert!(aligned + layout.size() > top);
+                continue;
+            }
+
+            prop_assert_eq!(ptr % layout.align(), 0);
+            prop_assert!(ptr >= previous_end, "allocation overlaps the previous one");
+            prop_assert!(ptr + layout.size() <= top, "allocation runs past the heap");
+            prop_assert_eq!(heap.position(), ptr + layout.size());
+            previous_end = ptr + layout.size();
+        }
+    }
+}
+
+#[test]
+fn first_allocation_starts_after_position_word() {
+    let len = 64;
+    let mut heap = HostHeap::new(len);
+    let allocator = heap.allocator(len);
+
+    let ptr = unsafe { allocator.alloc(Layout::new::<u8>()) } as usize;
+    assert_eq!(ptr, allocator.start + size_of::<usize>());
+    assert_eq!(heap.position(), ptr + 1);
+}
+
+#[test]
+fn position_word_holds_an_absolute_address() {
+    let len = 64;
+    let mut heap = HostHeap::new(len);
+    let allocator = heap.allocator(len);
+
+    // An address written by another allocator sharing the word is taken as is
+    let pos = allocator.start + 3 * size_of::<usize>();
+    heap.set_position(pos);
+    let ptr = unsafe { allocator.alloc(Layout::new::<u64>()) } as usize;
+    assert_eq!(ptr, pos);
+    assert_eq!(heap.position(), pos + size_of::<u64>());
+
+    // Below the bottom is not a position this allocator could have written
+    heap.set_position(allocator.start);
+    assert!(unsafe { allocator.alloc(Layout::new::<u8>()) }.is_null());
+    assert_eq!(heap.position(), allocator.start);
+}
+
+#[test]
+fn exact_fit_succeeds_and_one_more_byte_fails() {
+    let len = 64;
+    let mut heap = HostHeap::new(len);
+    let allocator = heap.allocator(len);
+    let free = len - size_of::<usize>();
+
+    assert!(!unsafe { allocator.alloc(Layout::from_size_align(free, 1).unwrap()) }.is_null());
+    assert!(unsafe { allocator.alloc(Layout::new::<u8>()) }.is_null());
+    assert!(!unsafe { allocator.alloc(Layout::from_size_align(0, 1).unwrap()) }.is_null());
+}
+
+#[test]
+fn position_near_end_of_address_space_fails_instead_of_wrapping() {
+    let len = 64;
+    let mut heap = HostHeap::new(len);
+    let allocator = heap.allocator(len);
+
+    for pos in [usize::MAX, usize::MAX - 1, usize::MAX - 7] {
+        heap.set_position(pos);
+        let ptr = unsafe { allocator.alloc(Layout::from_size_align(1, 8).unwrap()) };
+        assert!(ptr.is_null());
+        assert_eq!(heap.position(), pos);
+    }
+
+    heap.set_position(allocator.start + len - 1);
+    let huge = Layout::from_size_align(isize::MAX as usize, 1).unwrap();
+    assert!(unsafe { allocator.alloc(huge) }.is_null());
+}
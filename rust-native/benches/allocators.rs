@@ -0,0 +1,242 @@
+//! Criterion benchmarks for the allocators in this crate.
+//!
+//! Unlike the `optimize-bump-allocation` binary, which times a single run, criterion
+//! warms up, takes many samples and reports confidence intervals. Every benchmark
+//! frees what it allocated inside the timed closure: the bump allocators by `reset`,
+//! the system allocator by dropping its boxes.
+//!
+//! ```text
+//! cargo bench --bench allocators -- --save-baseline before
+//! cargo bench --bench allocators -- --baseline before
+//! ```
+
+use criterion::measurement::WallTime;
+use criterion::{
+    criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion, Throughput,
+};
+use optimize_bump_allocation::allocator::BumpAllocator;
+use optimize_bump_allocation::chunked_allocator::ChunkedBumpAllocator;
+use optimize_bump_allocation::collections::BumpVec;
+use optimize_bump_allocation::multi_thread_allocator::{
+    AtomicBumpAllocator, ThreadSafeBumpAllocator,
+};
+use optimize_bump_allocation::thread_local_arena::ArenaPool;
+use std::hint::black_box;
+use std::sync::Barrier;
+use std::time::{Duration, Instant};
+use std::{mem, thread};
+
+/// Allocations per iteration.
+const COUNT: usize = 10_000;
+
+/// 1024-byte value, like `LargeData` in the binary. Only its size matters.
+#[allow(dead_code)]
+struct LargeData([u64; 128]);
+
+fn single_values(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single_values");
+    group.throughput(Throughput::Elements(COUNT as u64));
+    bench_value(&mut group, "u8", |i| i as u8);
+    bench_value(&mut group, "u64", |i| i as u64);
+    bench_value(&mut group, "large_data", |i| LargeData([i as u64; 128]));
+    group.finish();
+}
+
+/// Allocate `COUNT` values made by `make` with the bump and system allocators.
+fn bench_value<T>(group: &mut BenchmarkGroup<WallTime>, name: &str, make: impl Fn(usize) -> T) {
+    let mut allocator = BumpAllocator::new(COUNT * mem::size_of::<T>().max(1));
+    group.bench_function(BenchmarkId::new("bump", name), |b| {
+        b.iter(|| {
+            let mut values = Vec::with_capacity(COUNT);
+            for i in 0..COUNT {
+                values.push(allocator.alloc(make(black_box(i))));
+            }
+            black_box(&values);
+            drop(values);
+            allocator.reset();
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("system", name), |b| {
+        b.iter(|| {
+            let mut values = Vec::with_capacity(COUNT);
+            for i in 0..COUNT {
+                values.push(Box::new(make(black_box(i))));
+            }
+            black_box(&values);
+        })
+    });
+}
+
+fn chunked(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunked");
+    group.throughput(Throughput::Elements(COUNT as u64));
+
+    // A new allocator each time, so every iteration pays for growing
+    group.bench_function("grow", |b| {
+        b.iter(|| {
+            let allocator = ChunkedBumpAllocator::new(4 * 1024);
+            for i in 0..COUNT {
+                black_box(allocator.alloc(black_box(i as u64)));
+            }
+        })
+    });
+
+    // After the first iteration the largest chunk kept by `reset` holds everything
+    let mut allocator = ChunkedBumpAllocator::new(4 * 1024);
+    group.bench_function("reuse", |b| {
+        b.iter(|| {
+            for i in 0..COUNT {
+                black_box(allocator.alloc(black_box(i as u64)));
+            }
+            allocator.reset();
+        })
+    });
+    group.finish();
+}
+
+fn collections(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vec_push");
+    group.throughput(Throughput::Elements(COUNT as u64));
+
+    // Growing by doubling leaves every smaller copy behind in the bump buffer
+    let mut allocator = BumpAllocator::new(COUNT * mem::size_of::<u64>() * 4);
+    group.bench_function("bump_vec", |b| {
+        b.iter(|| {
+            let mut values = BumpVec::new_in(&allocator);
+            for i in 0..COUNT {
+                values.push(black_box(i as u64));
+            }
+            black_box(&values);
+            drop(values);
+            allocator.reset();
+        })
+    });
+
+    group.bench_function("vec", |b| {
+        b.iter(|| {
+            let mut values = Vec::new();
+            for i in 0..COUNT {
+                values.push(black_box(i as u64));
+            }
+            black_box(&values);
+        })
+    });
+    group.finish();
+}
+
+fn threads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("threads");
+    group.sample_size(20);
+
+    for num_threads in [1, 2, 4] {
+        group.throughput(Throughput::Elements((num_threads * COUNT) as u64));
+        let buffer_size = num_threads * COUNT * mem::size_of::<u64>();
+
+        let allocator = ThreadSafeBumpAllocator::new(buffer_size);
+        group.bench_function(BenchmarkId::new("mutex", num_threads), |b| {
+            b.iter_custom(|iters| {
+                time_threads(
+                    iters,
+                    num_threads,
+                    || {
+                        let handle = allocator.handle();
+                        for i in 0..COUNT {
+                            black_box(handle.alloc(black_box(i as u64)));
+                        }
+                    },
+                    || allocator.reset(),
+                )
+            })
+        });
+
+        let allocator = AtomicBumpAllocator::new(buffer_size);
+        group.bench_function(BenchmarkId::new("atomic", num_threads), |b| {
+            b.iter_custom(|iters| {
+                time_threads(
+                    iters,
+                    num_threads,
+                    || {
+                        let handle = allocator.handle();
+                        for i in 0..COUNT {
+                            black_box(handle.alloc(black_box(i as u64)));
+                        }
+                    },
+                    || allocator.reset(),
+                )
+            })
+        });
+
+        let pool = ArenaPool::new(COUNT * mem::size_of::<u64>());
+        group.bench_function(BenchmarkId::new("arenas", num_threads), |b| {
+            b.iter_custom(|iters| {
+                time_threads(
+                    iters,
+                    num_threads,
+                    || {
+                        let arena = pool.arena();
+                        for i in 0..COUNT {
+                            black_box(arena.alloc(black_box(i as u64)));
+                        }
+                    },
+                    || pool.request_reset(),
+                )
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("system", num_threads), |b| {
+            b.iter_custom(|iters| {
+                time_threads(
+                    iters,
+                    num_threads,
+                    || {
+                        let mut values = Vec::with_capacity(COUNT);
+                        for i in 0..COUNT {
+                            values.push(Box::new(black_box(i as u64)));
+                        }
+                        black_box(&values);
+                    },
+                    || {},
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Time `iters` rounds of running `work` on `num_threads` threads at once. The threads
+/// are started once and wait at a barrier between rounds, so only the rounds are
+/// timed, not spawning and joining. `reset` runs untimed after each round, since the
+/// bump buffers only hold one round.
+fn time_threads(
+    iters: u64,
+    num_threads: usize,
+    work: impl Fn() + Sync,
+    reset: impl Fn(),
+) -> Duration {
+    let round_start = Barrier::new(num_threads + 1);
+    let round_end = Barrier::new(num_threads + 1);
+    let mut total = Duration::ZERO;
+    thread::scope(|scope| {
+        for _ in 0..num_threads {
+            scope.spawn(|| {
+                for _ in 0..iters {
+                    round_start.wait();
+                    work();
+                    round_end.wait();
+                }
+            });
+        }
+        for _ in 0..iters {
+            round_start.wait();
+            let start = Instant::now();
+            round_end.wait();
+            total += start.elapsed();
+            reset();
+        }
+    });
+    total
+}
+
+criterion_group!(benches, single_values, chunked, collections, threads);
+criterion_main!(benches);
@@ -1,90 +1,527 @@
+use bump_core::{check_align, Bump};
 use std::alloc::{alloc, dealloc, Layout};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
 use std::time::Instant;
-use std::{mem, ptr, usize};
+use std::{mem, ptr};
 
-const NUM_ALLOCATIONS: usize = 1_000_0000;
-const BUFFER_SIZE: usize = NUM_ALLOCATIONS * mem::size_of::<u64>() * 2;
+pub use bump_core::AllocError;
 
-use crate::align_up;
+use crate::backend::Backend;
+#[cfg(target_os = "linux")]
+use crate::mmap::{discard_pages, MmapOptions, MmapRegion};
+
+/// Block of `capacity` bytes from the global allocator, aligned to `align` and freed
+/// when dropped. The buffer behind [`BumpAllocator`], the shared allocators and the
+/// chunks of [`ChunkedBumpAllocator`](crate::chunked_allocator::ChunkedBumpAllocator).
+pub(crate) struct Buffer {
+    ptr: NonNull<u8>,
+    capacity: usize,
+    align: usize,
+}
+
+// SAFETY: the buffer is owned memory like a `Box<[u8]>`.
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+impl Buffer {
+    /// Allocate `capacity` bytes aligned to `align`, which must be a power of two.
+    pub(crate) fn new(capacity: usize, align: usize) -> Result<Self, AllocError> {
+        check_align(align)?;
+        let layout = Layout::from_size_align(capacity, align).map_err(|_| AllocError::Overflow)?;
+        let ptr = if capacity == 0 {
+            // `alloc` must not be called with a zero size, and no byte is ever handed out
+            NonNull::new(ptr::without_provenance_mut(align)).expect("alignment is not zero")
+        } else {
+            NonNull::new(unsafe { alloc(layout) }).ok_or(AllocError::OutOfMemory)?
+        };
+        Ok(Buffer {
+            ptr,
+            capacity,
+            align,
+        })
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if self.capacity > 0 {
+            unsafe {
+                dealloc(
+                    self.ptr.as_ptr(),
+                    Layout::from_size_align_unchecked(self.capacity, self.align),
+                );
+            }
+        }
+    }
+}
 
 pub struct BumpAllocator {
     buffer: *mut u8,
-    capacity: usize,
-    offset: RefCell<usize>,
+    /// Where `buffer` came from, and so how to free it.
+    backing: Backing,
+    bump: Bump<Cell<usize>>,
+    /// Largest `used()` seen at a reset.
+    high_water_mark: Cell<usize>,
+    /// Values from `alloc_with_drop` whose destructors are still to run, oldest first.
+    drops: RefCell<Vec<PendingDrop>>,
+    reset_policy: ResetPolicy,
+}
+
+/// What [`BumpAllocator::reset`] does with the memory it frees, besides making it
+/// available again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetPolicy {
+    /// Leave the old contents in place. The cheapest, but stale data stays readable
+    /// through raw pointers kept past the reset.
+    Plain,
+    /// Overwrite the used bytes with zeros.
+    Zero,
+    /// Overwrite the used bytes with [`POISON`] in debug builds, so reads of stale data
+    /// stand out. Release builds behave like `Plain`.
+    Poison,
+    /// Give the pages used beyond the first `threshold` bytes back to the operating
+    /// system, so a buffer that once filled up does not hold on to that memory. Only
+    /// whole pages are released, and only on Linux; elsewhere this behaves like `Plain`.
+    Release { threshold: usize },
+}
+
+/// Byte written over freed memory by [`ResetPolicy::Poison`].
+pub const POISON: u8 = 0xA5;
+
+enum Backing {
+    /// Held only so it is freed with the allocator; `buffer` points into it.
+    #[allow(dead_code)]
+    Heap(Buffer),
+    #[cfg(target_os = "linux")]
+    Mmap(MmapRegion),
+}
+
+/// A value in the buffer and the function that drops it.
+struct PendingDrop {
+    value: *mut u8,
+    drop: unsafe fn(*mut u8),
+}
+
+/// Panic with the reason an allocation failed, for the allocators' panicking
+/// counterparts of their `try_` methods.
+#[cold]
+pub(crate) fn alloc_failed(error: AllocError) -> ! {
+    panic!("{error}")
+}
+
+/// Move `value` into `block` and borrow it for `'a`.
+///
+/// # Safety
+///
+/// `block` must be valid for writes of a `T`, aligned for it, and not handed out
+/// again while the returned reference lives.
+pub(crate) unsafe fn write_value<'a, T>(block: NonNull<u8>, value: T) -> &'a mut T {
+    let ptr = block.cast::<T>().as_ptr();
+    ptr::write(ptr, value);
+    &mut *ptr
+}
+
+unsafe fn drop_value<T>(value: *mut u8) {
+    ptr::drop_in_place(value.cast::<T>());
 }
 
 impl BumpAllocator {
     pub fn new(capacity: usize) -> Self {
-        let layout =
-            Layout::from_size_align(capacity, mem::align_of::<usize>()).expect("Invalid layout");
-        let buffer = unsafe { alloc(layout) };
-        if buffer.is_null() {
-            panic!("failed to allocate buffer for PumpAllocator");
+        Self::with_alignment(capacity, mem::align_of::<usize>())
+    }
+
+    /// Like [`new`](Self::new), but the buffer starts at a multiple of `align`, which
+    /// must be a power of two. Blocks are aligned by address either way; with a base
+    /// alignment at least as large as theirs, none of the capacity goes to padding
+    /// before the first block.
+    pub fn with_alignment(capacity: usize, align: usize) -> Self {
+        let heap = Buffer::new(capacity, align)
+            .unwrap_or_else(|error| panic!("failed to allocate buffer for BumpAllocator: {error}"));
+        let buffer = heap.as_ptr();
+
+        BumpAllocator {
+            buffer,
+            backing: Backing::Heap(heap),
+            bump: Bump::new(buffer as usize, capacity, Cell::new(0)),
+            high_water_mark: Cell::new(0),
+            drops: RefCell::new(Vec::new()),
+            reset_policy: ResetPolicy::Plain,
         }
+    }
+
+    /// Like [`new`](Self::new), but the buffer is an anonymous memory mapping instead
+    /// of a heap block. Mapping reserves address space only: pages are committed when
+    /// first touched, so a large buffer costs nothing up front. The reset policy is
+    /// `Release { threshold: 0 }`: `reset` hands the used pages back to the kernel
+    /// with `MADV_DONTNEED`, so they are committed again on the next fill.
+    ///
+    /// The buffer is page-aligned, or aligned to 2 MiB with `options.huge_pages`.
+    #[cfg(target_os = "linux")]
+    pub fn with_mmap(capacity: usize, options: MmapOptions) -> Self {
+        let region = MmapRegion::new(capacity, options)
+            .unwrap_or_else(|error| panic!("failed to map buffer for BumpAllocator: {}", error));
+        let buffer = region.as_ptr();
 
         BumpAllocator {
             buffer,
-            capacity,
-            offset: RefCell::new(0),
+            backing: Backing::Mmap(region),
+            bump: Bump::new(buffer as usize, capacity, Cell::new(0)),
+            high_water_mark: Cell::new(0),
+            drops: RefCell::new(Vec::new()),
+            reset_policy: ResetPolicy::Release { threshold: 0 },
         }
     }
 
-    pub fn allocate(&self, size: usize, align: usize) -> *mut u8 {
-        let mut current_offset = self.offset.borrow_mut();
-        let start = align_up(*current_offset, align);
-        let end = start
-            .checked_add(size)
-            .expect("overflow in allocation size");
+    /// Choose what `reset` does with the freed memory. New allocators start with
+    /// `Plain`, or `Release { threshold: 0 }` for [`with_mmap`](Self::with_mmap).
+    pub fn set_reset_policy(&mut self, policy: ResetPolicy) {
+        self.reset_policy = policy;
+    }
+
+    pub fn reset_policy(&self) -> ResetPolicy {
+        self.reset_policy
+    }
 
-        if end > self.capacity {
-            panic!("BumpAllocator out of memory")
+    /// Move `value` into the allocator. The reference borrows the allocator, so it
+    /// cannot be used after `reset`. The value's destructor is never run; use
+    /// [`alloc_with_drop`](Self::alloc_with_drop) for values that own resources.
+    ///
+    /// ```compile_fail
+    /// use optimize_bump_allocation::allocator::BumpAllocator;
+    ///
+    /// let mut bump = BumpAllocator::new(64);
+    /// let value = bump.alloc(1u64);
+    /// bump.reset(); // error: `bump` is still borrowed by `value`
+    /// *value += 1;
+    /// ```
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        unsafe {
+            let ptr = self.allocate(mem::size_of::<T>(), mem::align_of::<T>()) as *mut T;
+            ptr::write(ptr, value);
+            &mut *ptr
         }
+    }
 
-        *current_offset = end;
-        unsafe { self.buffer.add(start) }
+    /// Like [`alloc`](Self::alloc), but reports which limit was hit instead of panicking.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T>(&self, value: T) -> Result<&mut T, AllocError> {
+        unsafe {
+            let block = self.try_allocate(mem::size_of::<T>(), mem::align_of::<T>())?;
+            Ok(write_value(block, value))
+        }
+    }
+
+    /// Like [`alloc`](Self::alloc), but the value's destructor runs on `reset` or when
+    /// the allocator is dropped, in the reverse order of allocation. Types that do not
+    /// need dropping are not recorded, so this costs nothing extra for them.
+    ///
+    /// `T: 'static` because the destructor may run long after the value was allocated,
+    /// when anything it borrowed could be gone.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_with_drop<T: 'static>(&self, value: T) -> &mut T {
+        let value = self.alloc(value);
+        self.record_drop(value);
+        value
+    }
+
+    /// Like [`alloc_with_drop`](Self::alloc_with_drop), but reports which limit was
+    /// hit instead of panicking. On failure `value` is dropped right away.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc_with_drop<T: 'static>(&self, value: T) -> Result<&mut T, AllocError> {
+        let value = self.try_alloc(value)?;
+        self.record_drop(value);
+        Ok(value)
+    }
+
+    fn record_drop<T>(&self, value: &mut T) {
+        if mem::needs_drop::<T>() {
+            self.drops.borrow_mut().push(PendingDrop {
+                value: (value as *mut T).cast(),
+                drop: drop_value::<T>,
+            });
+        }
+    }
+
+    /// Number of values whose destructors will run on the next `reset`.
+    pub fn pending_drops(&self) -> usize {
+        self.drops.borrow().len()
     }
 
-    pub fn reset(&self) {
-        let mut current_offset = self.offset.borrow_mut();
-        *current_offset = 0;
+    /// Run the recorded destructors beyond the first `keep`, newest first. A destructor
+    /// that panics leaves the older ones recorded.
+    fn run_drops(&self, keep: usize) {
+        loop {
+            // Not borrowed while the destructor runs
+            let pending = {
+                let mut drops = self.drops.borrow_mut();
+                if drops.len() <= keep {
+                    return;
+                }
+                drops.pop().unwrap()
+            };
+            unsafe { (pending.drop)(pending.value) }
+        }
+    }
+
+    /// Copy `values` into the allocator.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, values: &[T]) -> &mut [T] {
+        unsafe {
+            let ptr = self.allocate(mem::size_of_val(values), mem::align_of::<T>()) as *mut T;
+            ptr::copy_nonoverlapping(values.as_ptr(), ptr, values.len());
+            std::slice::from_raw_parts_mut(ptr, values.len())
+        }
+    }
+
+    /// Copy `s` into the allocator.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, s: &str) -> &mut str {
+        let bytes = self.alloc_slice_copy(s.as_bytes());
+        unsafe { std::str::from_utf8_unchecked_mut(bytes) }
+    }
+
+    /// Reserve `size` bytes aligned to `align` and return a raw pointer to them.
+    ///
+    /// # Safety
+    ///
+    /// Unlike [`alloc`](Self::alloc), the pointer does not borrow the allocator. The
+    /// caller must not use it after `reset` or after the allocator is dropped.
+    pub unsafe fn allocate(&self, size: usize, align: usize) -> *mut u8 {
+        self.try_allocate(size, align)
+            .unwrap_or_else(|error| alloc_failed(error))
+            .as_ptr()
+    }
+
+    /// Like [`allocate`](Self::allocate), but reports which limit was hit instead of
+    /// panicking.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`allocate`](Self::allocate).
+    pub unsafe fn try_allocate(&self, size: usize, align: usize) -> Result<NonNull<u8>, AllocError> {
+        let offset = self.bump.alloc(size, align)?;
+        Ok(NonNull::new_unchecked(self.buffer.add(offset)))
+    }
+
+    /// Free every allocation at once, running the destructors of values from
+    /// [`alloc_with_drop`](Self::alloc_with_drop) first. Takes `&mut self` so nothing
+    /// borrowed from the allocator, such as a `BumpVec`, can still be alive. The freed
+    /// memory is then treated as the [`ResetPolicy`] says.
+    pub fn reset(&mut self) {
+        self.rewind(0, 0);
+    }
+
+    /// Run `f` in a frame that frees everything allocated in it when `f` returns, or
+    /// unwinds, and leaves earlier allocations alone. Frames nest with
+    /// [`Frame::scope`], so each phase of a computation can free its own temporaries.
+    ///
+    /// Allocations borrow the frame, so they cannot outlive it:
+    ///
+    /// ```compile_fail
+    /// use optimize_bump_allocation::allocator::BumpAllocator;
+    ///
+    /// let mut bump = BumpAllocator::new(64);
+    /// let value = bump.scope(|frame| frame.alloc(1u64)); // error: escapes the frame
+    /// ```
+    ///
+    /// Freed memory is treated as the [`ResetPolicy`] says, as on `reset`.
+    pub fn scope<R>(&mut self, f: impl for<'f> FnOnce(&mut Frame<'f>) -> R) -> R {
+        enter_scope(self, f)
+    }
+
+    /// Free everything allocated after `offset`, running the destructors recorded after
+    /// the first `drops` first.
+    fn rewind(&self, offset: usize, drops: usize) {
+        self.run_drops(drops);
+        let used = self.bump.used();
+        self.high_water_mark.set(self.high_water_mark.get().max(used));
+        self.apply_reset_policy(offset, used);
+        self.bump.position().set(offset);
+    }
+
+    /// Treat the bytes from `from` to `to` as `reset_policy` says.
+    fn apply_reset_policy(&self, from: usize, to: usize) {
+        match self.reset_policy {
+            ResetPolicy::Plain => {}
+            ResetPolicy::Zero => unsafe { self.buffer.add(from).write_bytes(0, to - from) },
+            ResetPolicy::Poison => {
+                if cfg!(debug_assertions) {
+                    unsafe { self.buffer.add(from).write_bytes(POISON, to - from) }
+                }
+            }
+            #[cfg(target_os = "linux")]
+            ResetPolicy::Release { threshold } => {
+                let from = from.max(threshold);
+                if to <= from {
+                    return;
+                }
+                match &self.backing {
+                    Backing::Mmap(region) => region.discard(from, to),
+                    // Only whole pages inside the heap block are released
+                    Backing::Heap(_) => unsafe { discard_pages(self.buffer.add(from), to - from) },
+                }
+            }
+            #[cfg(not(target_os = "linux"))]
+            ResetPolicy::Release { .. } => {}
+        }
+    }
+
+    /// Bytes consumed since the last reset, alignment padding included.
+    pub fn used(&self) -> usize {
+        self.bump.used()
+    }
+
+    /// Bytes still free in the buffer.
+    pub fn remaining(&self) -> usize {
+        self.bump.capacity() - self.bump.used()
+    }
+
+    /// Most bytes ever consumed between two resets.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark.get().max(self.bump.used())
+    }
+}
+
+/// Lets standard collections allocate from the bump buffer, e.g. `Vec::new_in(&bump)`.
+/// Freed blocks are only reclaimed by `reset`.
+#[cfg(feature = "nightly")]
+unsafe impl std::alloc::Allocator for BumpAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, std::alloc::AllocError> {
+        let block = unsafe { self.try_allocate(layout.size(), layout.align()) }
+            .map_err(|_| std::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(block, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
+        // Bump allocator does not support deallocation
     }
 }
 
 impl Drop for BumpAllocator {
     fn drop(&mut self) {
-        let layout = Layout::from_size_align(self.capacity, mem::align_of::<usize>())
-            .expect("Invalid layout");
-        unsafe {
-            dealloc(self.buffer, layout);
-        }
+        // The buffer or mapping is freed when `backing` drops
+        self.run_drops(0);
     }
 }
 
-pub fn measure_bump_allocator() {
-    let allocator = BumpAllocator::new(BUFFER_SIZE);
-    let mut allocations: Vec<*mut u64> = Vec::with_capacity(NUM_ALLOCATIONS);
+/// Part of a [`BumpAllocator`] that is freed as a whole when the frame ends, see
+/// [`BumpAllocator::scope`].
+///
+/// `'f` only names this frame: it is invariant, so references allocated in one frame
+/// cannot be passed off as living in another.
+pub struct Frame<'f> {
+    allocator: &'f BumpAllocator,
+    _frame: PhantomData<fn(&'f ()) -> &'f ()>,
+}
+
+/// Rewinds the allocator to where a frame started, also when the frame unwinds.
+struct Rewind<'a> {
+    allocator: &'a BumpAllocator,
+    offset: usize,
+    drops: usize,
+}
+
+impl Drop for Rewind<'_> {
+    fn drop(&mut self) {
+        self.allocator.rewind(self.offset, self.drops);
+    }
+}
+
+fn enter_scope<R>(
+    allocator: &BumpAllocator,
+    f: impl for<'f> FnOnce(&mut Frame<'f>) -> R,
+) -> R {
+    let _rewind = Rewind {
+        allocator,
+        offset: allocator.bump.used(),
+        drops: allocator.drops.borrow().len(),
+    };
+    f(&mut Frame {
+        allocator,
+        _frame: PhantomData,
+    })
+}
+
+impl<'f> Frame<'f> {
+    /// Like [`BumpAllocator::alloc`], freed when the frame ends.
+    pub fn alloc<T>(&self, value: T) -> &'f mut T {
+        self.allocator.alloc(value)
+    }
+
+    /// Like [`BumpAllocator::try_alloc`], freed when the frame ends.
+    pub fn try_alloc<T>(&self, value: T) -> Result<&'f mut T, AllocError> {
+        self.allocator.try_alloc(value)
+    }
+
+    /// Like [`BumpAllocator::alloc_with_drop`]; the destructor runs when the frame ends.
+    pub fn alloc_with_drop<T: 'static>(&self, value: T) -> &'f mut T {
+        self.allocator.alloc_with_drop(value)
+    }
+
+    /// Like [`BumpAllocator::alloc_slice_copy`], freed when the frame ends.
+    pub fn alloc_slice_copy<T: Copy>(&self, values: &[T]) -> &'f mut [T] {
+        self.allocator.alloc_slice_copy(values)
+    }
+
+    /// Like [`BumpAllocator::alloc_str`], freed when the frame ends.
+    pub fn alloc_str(&self, s: &str) -> &'f mut str {
+        self.allocator.alloc_str(s)
+    }
+
+    /// Run `f` in a frame nested in this one. Takes `&mut self`, so this frame cannot
+    /// allocate until the inner one has ended, while its earlier allocations stay
+    /// usable.
+    ///
+    /// ```compile_fail
+    /// use optimize_bump_allocation::allocator::BumpAllocator;
+    ///
+    /// let mut bump = BumpAllocator::new(64);
+    /// bump.scope(|outer| {
+    ///     let mut slot = None;
+    ///     outer.scope(|inner| slot = Some(inner.alloc(1u64))); // error: escapes `inner`
+    /// });
+    /// ```
+    pub fn scope<R>(&mut self, f: impl for<'g> FnOnce(&mut Frame<'g>) -> R) -> R {
+        enter_scope(self.allocator, f)
+    }
+
+    /// Bytes consumed in the allocator, this frame and the ones around it included.
+    pub fn used(&self) -> usize {
+        self.allocator.used()
+    }
+}
+
+pub fn measure_bump_allocator(num_allocations: usize) {
+    let allocator = BumpAllocator::new(num_allocations * mem::size_of::<u64>() * 2);
+    let mut allocations: Vec<&mut u64> = Vec::with_capacity(num_allocations);
     let start = Instant::now();
-    for i in 0..NUM_ALLOCATIONS {
-        let pointer = allocator.allocate(mem::size_of::<u64>(), mem::size_of::<u64>()) as *mut u64;
-        unsafe {
-            ptr::write(pointer as *mut u64, i as u64);
-        }
-        allocations.push(pointer);
+    for i in 0..num_allocations {
+        allocations.push(allocator.alloc(i as u64));
     }
     let duration = start.elapsed();
 
     println!(
         "Bump Allocator: Allocated {} u64 in {:?}",
-        NUM_ALLOCATIONS, duration
+        num_allocations, duration
     );
 }
 
-pub fn measure_standard_allocator() {
-    let mut allocations: Vec<*mut u64> = Vec::with_capacity(NUM_ALLOCATIONS);
+pub fn measure_standard_allocator(num_allocations: usize) {
+    let mut allocations: Vec<*mut u64> = Vec::with_capacity(num_allocations);
 
     let start = Instant::now();
-    for i in 0..NUM_ALLOCATIONS {
+    for i in 0..num_allocations {
         let boxed = Box::new(i as u64);
         allocations.push(Box::into_raw(boxed));
     }
@@ -92,7 +529,7 @@ pub fn measure_standard_allocator() {
     let duration = start.elapsed();
     println!(
         "Standard Allocator: Allocated {} u64 in {:?}",
-        NUM_ALLOCATIONS, duration
+        num_allocations, duration
     );
 
     // clean up
@@ -103,10 +540,15 @@ pub fn measure_standard_allocator() {
     }
 }
 
-pub fn run() {
-    println!("Measure Bump Allocator...");
-    measure_bump_allocator();
+/// Allocate `num_allocations` u64s with each of the Bump and System `backends`.
+pub fn run(num_allocations: usize, backends: &[Backend]) {
+    if backends.contains(&Backend::Bump) {
+        println!("Measure Bump Allocator...");
+        measure_bump_allocator(num_allocations);
+    }
 
-    println!("\nMeasure Standard Allocator...");
-    measure_standard_allocator();
+    if backends.contains(&Backend::System) {
+        println!("\nMeasure Standard Allocator...");
+        measure_standard_allocator(num_allocations);
+    }
 }
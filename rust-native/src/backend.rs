@@ -0,0 +1,30 @@
+//! Allocators a benchmark can be asked to run. Each benchmark skips the backends it
+//! does not compare.
+
+/// One allocator implementation, as selected on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// Single-threaded `BumpAllocator` over one fixed buffer.
+    Bump,
+    /// `ChunkedBumpAllocator`, which grows on demand.
+    Chunked,
+    /// `ThreadSafeBumpAllocator`, with the position behind a mutex.
+    Mutex,
+    /// `AtomicBumpAllocator`, with a lock-free position.
+    Atomic,
+    /// One `ThreadArena` per thread from a shared `ArenaPool`.
+    Arenas,
+    /// The system allocator, through `Box`.
+    System,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 6] = [
+        Backend::Bump,
+        Backend::Chunked,
+        Backend::Mutex,
+        Backend::Atomic,
+        Backend::Arenas,
+        Backend::System,
+    ];
+}
@@ -0,0 +1,171 @@
+use bump_core::{check_align, Bump};
+use std::cell::{Cell, RefCell};
+use std::ptr::NonNull;
+use std::{cmp, mem};
+
+use crate::allocator::{alloc_failed, write_value, AllocError, Buffer};
+
+/// One buffer obtained from the system allocator, bumped through on its own.
+pub(crate) struct Chunk {
+    buffer: Buffer,
+    bump: Bump<Cell<usize>>,
+}
+
+impl Chunk {
+    pub(crate) fn new(capacity: usize) -> Result<Self, AllocError> {
+        let buffer = Buffer::new(capacity, mem::align_of::<usize>())?;
+        Ok(Chunk {
+            bump: Bump::new(buffer.as_ptr() as usize, capacity, Cell::new(0)),
+            buffer,
+        })
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub(crate) fn used(&self) -> usize {
+        self.bump.used()
+    }
+
+    pub(crate) fn try_allocate(
+        &self,
+        size: usize,
+        align: usize,
+    ) -> Result<NonNull<u8>, AllocError> {
+        let offset = self.bump.alloc(size, align)?;
+        Ok(unsafe { NonNull::new_unchecked(self.buffer.as_ptr().add(offset)) })
+    }
+
+    pub(crate) fn reset(&self) {
+        self.bump.reset();
+    }
+}
+
+/// Bump allocator that grows instead of running out.
+///
+/// When the current chunk is full a new one is taken from the system allocator, each
+/// twice the size of the last, so there is no need to guess the total size up front.
+/// An optional memory limit caps the bytes held across all chunks.
+pub struct ChunkedBumpAllocator {
+    chunks: RefCell<Vec<Chunk>>,
+    first_chunk_size: usize,
+    memory_limit: Option<usize>,
+}
+
+impl ChunkedBumpAllocator {
+    pub fn new(first_chunk_size: usize) -> Self {
+        ChunkedBumpAllocator {
+            chunks: RefCell::new(Vec::new()),
+            first_chunk_size: cmp::max(first_chunk_size, mem::size_of::<usize>()),
+            memory_limit: None,
+        }
+    }
+
+    /// Like [`new`](Self::new), but never holds more than `memory_limit` bytes of chunks.
+    pub fn with_memory_limit(first_chunk_size: usize, memory_limit: usize) -> Self {
+        ChunkedBumpAllocator {
+            memory_limit: Some(memory_limit),
+            ..ChunkedBumpAllocator::new(first_chunk_size)
+        }
+    }
+
+    /// Move `value` into the allocator. The reference borrows the allocator, so it
+    /// cannot be used after `reset`. The value's destructor is never run.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        self.try_alloc(value)
+            .unwrap_or_else(|error| alloc_failed(error))
+    }
+
+    /// Like [`alloc`](Self::alloc), but reports which limit was hit instead of panicking.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T>(&self, value: T) -> Result<&mut T, AllocError> {
+        unsafe {
+            let block = self.try_allocate(mem::size_of::<T>(), mem::align_of::<T>())?;
+            Ok(write_value(block, value))
+        }
+    }
+
+    /// Reserve `size` bytes aligned to `align`, adding a chunk if the current one is full.
+    ///
+    /// # Safety
+    ///
+    /// The pointer does not borrow the allocator. The caller must not use it after
+    /// `reset` or after the allocator is dropped.
+    pub unsafe fn try_allocate(
+        &self,
+        size: usize,
+        align: usize,
+    ) -> Result<NonNull<u8>, AllocError> {
+        // Checked before a chunk is added for a request that could never be served
+        check_align(align)?;
+        let mut chunks = self.chunks.borrow_mut();
+        if let Some(chunk) = chunks.last() {
+            match chunk.try_allocate(size, align) {
+                Err(AllocError::OutOfMemory) => {}
+                result => return result,
+            }
+        }
+
+        let chunk = Chunk::new(self.next_chunk_size(&chunks, size, align)?)?;
+        let block = chunk.try_allocate(size, align);
+        chunks.push(chunk);
+        block
+    }
+
+    /// Size of the chunk to add for a `size`/`align` request: double the last chunk,
+    /// at least big enough for the request, and within the memory limit.
+    fn next_chunk_size(
+        &self,
+        chunks: &[Chunk],
+        size: usize,
+        align: usize,
+    ) -> Result<usize, AllocError> {
+        // Chunks are only aligned to `usize`, so leave room to align the block
+        let required = size
+            .checked_add(align.saturating_sub(mem::align_of::<usize>()))
+            .ok_or(AllocError::Overflow)?;
+        let doubled = chunks.last().map_or(self.first_chunk_size, |chunk| {
+            chunk.capacity().saturating_mul(2)
+        });
+        let chunk_size = cmp::max(doubled, required);
+
+        let Some(limit) = self.memory_limit else {
+            return Ok(chunk_size);
+        };
+        let available = limit.saturating_sub(Self::total(chunks));
+        if required > available || available == 0 {
+            return Err(AllocError::OutOfMemory);
+        }
+        Ok(cmp::min(chunk_size, available))
+    }
+
+    fn total(chunks: &[Chunk]) -> usize {
+        chunks.iter().map(Chunk::capacity).sum()
+    }
+
+    /// Free every allocation at once, keeping only the largest chunk for reuse.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        if let Some(largest) = chunks.drain(..).max_by_key(Chunk::capacity) {
+            largest.reset();
+            chunks.push(largest);
+        }
+    }
+
+    /// Bytes held in chunks, used or not.
+    pub fn allocated_bytes(&self) -> usize {
+        Self::total(&self.chunks.borrow())
+    }
+
+    /// Bytes consumed in all chunks, alignment padding included. Space left unused at
+    /// the end of a full chunk is not counted.
+    pub fn used(&self) -> usize {
+        self.chunks.borrow().iter().map(Chunk::used).sum()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+}
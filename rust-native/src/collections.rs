@@ -0,0 +1,291 @@
+//! Collections that allocate from a [`BumpAllocator`] on stable Rust.
+//!
+//! Each collection borrows the allocator for as long as it lives, so the allocator
+//! cannot be reset or dropped underneath it:
+//!
+//! ```compile_fail
+//! use optimize_bump_allocation::{allocator::BumpAllocator, collections::BumpVec};
+//!
+//! let mut bump = BumpAllocator::new(1024);
+//! let mut values = BumpVec::new_in(&bump);
+//! values.push(1u64);
+//! bump.reset(); // error: `bump` is still borrowed by `values`
+//! values.push(2);
+//! ```
+//!
+//! Memory is only given back by `reset`, but dropping a collection still runs the
+//! destructors of the values it owns.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+use std::{cmp, slice, str};
+
+use crate::allocator::BumpAllocator;
+
+/// Owned value stored in a [`BumpAllocator`], like `Box<T>`.
+pub struct BumpBox<'a, T> {
+    ptr: NonNull<T>,
+    _marker: PhantomData<(&'a BumpAllocator, T)>,
+}
+
+impl<'a, T> BumpBox<'a, T> {
+    pub fn new_in(value: T, allocator: &'a BumpAllocator) -> Self {
+        // The box borrows the allocator, so the block outlives it
+        unsafe {
+            let ptr = allocator.allocate(mem::size_of::<T>(), mem::align_of::<T>()) as *mut T;
+            ptr::write(ptr, value);
+            BumpBox {
+                ptr: NonNull::new_unchecked(ptr),
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    /// Move the value back out of the allocator.
+    pub fn into_inner(self) -> T {
+        let this = ManuallyDrop::new(self);
+        unsafe { ptr::read(this.ptr.as_ptr()) }
+    }
+}
+
+impl<T> Deref for BumpBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for BumpBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for BumpBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()) }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BumpBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Growable array stored in a [`BumpAllocator`], like `Vec<T>`.
+///
+/// Growing copies the elements into a new block; the old block stays in the
+/// allocator until the next `reset`.
+pub struct BumpVec<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+    allocator: &'a BumpAllocator,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T> BumpVec<'a, T> {
+    pub fn new_in(allocator: &'a BumpAllocator) -> Self {
+        BumpVec {
+            ptr: NonNull::dangling(),
+            len: 0,
+            // Zero-sized values never need memory
+            capacity: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+            allocator,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, allocator: &'a BumpAllocator) -> Self {
+        let mut vec = BumpVec::new_in(allocator);
+        vec.reserve(capacity);
+        vec
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Make room for at least `additional` more elements.
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len.checked_add(additional).expect("capacity overflow");
+        if required > self.capacity {
+            self.grow(cmp::max(required, self.capacity * 2));
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == self.capacity {
+            self.grow(cmp::max(4, self.capacity * 2));
+        }
+        unsafe { ptr::write(self.ptr.as_ptr().add(self.len), value) };
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(unsafe { ptr::read(self.ptr.as_ptr().add(self.len)) })
+    }
+
+    /// Drop the elements past `len`.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let tail =
+            ptr::slice_from_raw_parts_mut(unsafe { self.ptr.as_ptr().add(len) }, self.len - len);
+        self.len = len;
+        unsafe { ptr::drop_in_place(tail) }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    fn grow(&mut self, capacity: usize) {
+        let size = mem::size_of::<T>()
+            .checked_mul(capacity)
+            .expect("capacity overflow");
+        unsafe {
+            let ptr = self.allocator.allocate(size, mem::align_of::<T>()) as *mut T;
+            ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr, self.len);
+            self.ptr = NonNull::new_unchecked(ptr);
+        }
+        self.capacity = capacity;
+    }
+}
+
+impl<T: Copy> BumpVec<'_, T> {
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        self.reserve(values.len());
+        unsafe {
+            ptr::copy_nonoverlapping(
+                values.as_ptr(),
+                self.ptr.as_ptr().add(self.len),
+                values.len(),
+            );
+        }
+        self.len += values.len();
+    }
+}
+
+impl<T> Deref for BumpVec<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for BumpVec<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Extend<T> for BumpVec<'_, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T> Drop for BumpVec<'_, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BumpVec<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// UTF-8 string stored in a [`BumpAllocator`], like `String`.
+pub struct BumpString<'a> {
+    bytes: BumpVec<'a, u8>,
+}
+
+impl<'a> BumpString<'a> {
+    pub fn new_in(allocator: &'a BumpAllocator) -> Self {
+        BumpString {
+            bytes: BumpVec::new_in(allocator),
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, allocator: &'a BumpAllocator) -> Self {
+        BumpString {
+            bytes: BumpVec::with_capacity_in(capacity, allocator),
+        }
+    }
+
+    pub fn from_str_in(s: &str, allocator: &'a BumpAllocator) -> Self {
+        let mut string = BumpString::with_capacity_in(s.len(), allocator);
+        string.push_str(s);
+        string
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.bytes.capacity()
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole `str`s and `char`s are ever pushed
+        unsafe { str::from_utf8_unchecked(&self.bytes) }
+    }
+}
+
+impl Deref for BumpString<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Write for BumpString<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl fmt::Display for BumpString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for BumpString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
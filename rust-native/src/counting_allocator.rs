@@ -0,0 +1,181 @@
+//! Global allocator wrapper that counts what goes through another allocator, so the
+//! benchmarks can report the system allocator's calls and memory use next to the bump
+//! allocators'.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// Counters of the allocator passed to [`register`].
+static REGISTERED: OnceLock<&'static AllocCounters> = OnceLock::new();
+
+/// `A` with counters for allocations, frees, reallocations, live bytes and peak bytes.
+///
+/// Installed as the global allocator and then [`register`]ed, so the benchmarks can
+/// find its counters:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static GLOBAL: CountingAlloc<System> = CountingAlloc::new(System);
+///
+/// fn main() {
+///     counting_allocator::register(&GLOBAL);
+/// }
+/// ```
+///
+/// Only requested sizes are counted. The headers and size classes of `A` are not
+/// visible through `GlobalAlloc`.
+pub struct CountingAlloc<A> {
+    inner: A,
+    counters: AllocCounters,
+}
+
+/// Counters updated by a [`CountingAlloc`].
+pub struct AllocCounters {
+    allocations: AtomicUsize,
+    frees: AtomicUsize,
+    reallocs: AtomicUsize,
+    live_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+}
+
+/// Snapshot of [`AllocCounters`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Successful `alloc` and `alloc_zeroed` calls.
+    pub allocations: usize,
+    /// `dealloc` calls.
+    pub frees: usize,
+    /// Successful `realloc` calls.
+    pub reallocs: usize,
+    /// Bytes allocated and not yet freed.
+    pub live_bytes: usize,
+    /// Most bytes live at once since the start or the last
+    /// [`reset_peak`](AllocCounters::reset_peak).
+    pub peak_bytes: usize,
+}
+
+impl<A> CountingAlloc<A> {
+    pub const fn new(inner: A) -> Self {
+        CountingAlloc {
+            inner,
+            counters: AllocCounters {
+                allocations: AtomicUsize::new(0),
+                frees: AtomicUsize::new(0),
+                reallocs: AtomicUsize::new(0),
+                live_bytes: AtomicUsize::new(0),
+                peak_bytes: AtomicUsize::new(0),
+            },
+        }
+    }
+
+    /// The wrapped allocator.
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    pub fn counters(&self) -> &AllocCounters {
+        &self.counters
+    }
+}
+
+/// Make the counters of `allocator`, normally the global allocator, available through
+/// [`registered`]. Only the first call has an effect.
+pub fn register<A>(allocator: &'static CountingAlloc<A>) {
+    let _ = REGISTERED.set(&allocator.counters);
+}
+
+/// Counters passed to [`register`], or `None` if nothing is being counted.
+pub fn registered() -> Option<&'static AllocCounters> {
+    REGISTERED.get().copied()
+}
+
+impl AllocCounters {
+    pub fn stats(&self) -> AllocStats {
+        AllocStats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            frees: self.frees.load(Ordering::Relaxed),
+            reallocs: self.reallocs.load(Ordering::Relaxed),
+            live_bytes: self.live_bytes.load(Ordering::Relaxed),
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Restart the peak from the bytes live now, to measure the peak of one phase.
+    pub fn reset_peak(&self) {
+        self.peak_bytes
+            .store(self.live_bytes.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    fn grow(&self, bytes: usize) {
+        let live = self.live_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak_bytes.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.live_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+impl AllocStats {
+    /// Calls made between `earlier` and `self`. Live and peak bytes are taken from
+    /// `self`.
+    pub fn since(self, earlier: AllocStats) -> AllocStats {
+        AllocStats {
+            allocations: self.allocations - earlier.allocations,
+            frees: self.frees - earlier.frees,
+            reallocs: self.reallocs - earlier.reallocs,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for AllocStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} allocations, {} frees, {} reallocs, {} bytes live, {} bytes peak",
+            self.allocations, self.frees, self.reallocs, self.live_bytes, self.peak_bytes
+        )
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.counters.allocations.fetch_add(1, Ordering::Relaxed);
+            self.counters.grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            self.counters.allocations.fetch_add(1, Ordering::Relaxed);
+            self.counters.grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        self.counters.frees.fetch_add(1, Ordering::Relaxed);
+        self.counters.shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            self.counters.reallocs.fetch_add(1, Ordering::Relaxed);
+            if new_size > layout.size() {
+                self.counters.grow(new_size - layout.size());
+            } else {
+                self.counters.shrink(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
@@ -0,0 +1,146 @@
+use bump_core::Bump;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// Alignment of the region. Larger alignments are served by the fallback.
+pub const REGION_ALIGN: usize = 4096;
+
+/// Bump allocator that a binary can install as its global allocator:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static GLOBAL: GlobalBumpAllocator = GlobalBumpAllocator::new(64 * 1024 * 1024);
+/// ```
+///
+/// The region is taken from the system allocator on the first allocation. For a large
+/// capacity that is a fresh mapping, whose pages the operating system only backs once
+/// they are touched. Allocations go to the region until it is full and to the system
+/// allocator after that. `dealloc` tells the two apart by address: blocks in the region
+/// are never freed, blocks outside it are handed back to the system allocator.
+pub struct GlobalBumpAllocator {
+    /// Start of the region, or null until the first allocation.
+    region: AtomicPtr<u8>,
+    /// Offsets into the region. The start is 0 because the region's address is not
+    /// known in a `const fn`; offsets aligned up to `REGION_ALIGN` or less are aligned
+    /// addresses because the region is.
+    bump: Bump<AtomicUsize>,
+    /// Allocations served by the system allocator because the region could not.
+    fallbacks: AtomicUsize,
+}
+
+impl GlobalBumpAllocator {
+    pub const fn new(capacity: usize) -> Self {
+        GlobalBumpAllocator {
+            region: AtomicPtr::new(ptr::null_mut()),
+            bump: Bump::new(0, capacity, AtomicUsize::new(0)),
+            fallbacks: AtomicUsize::new(0),
+        }
+    }
+
+    /// Whether `ptr` points into the region.
+    pub fn contains(&self, ptr: *const u8) -> bool {
+        let start = self.region.load(Ordering::Acquire) as usize;
+        start != 0 && (start..start + self.bump.capacity()).contains(&(ptr as usize))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.bump.capacity()
+    }
+
+    /// Bytes of the region consumed so far, alignment padding included.
+    pub fn used(&self) -> usize {
+        self.bump.used()
+    }
+
+    /// Allocations the region could not serve.
+    pub fn fallbacks(&self) -> usize {
+        self.fallbacks.load(Ordering::Relaxed)
+    }
+
+    /// Start of the region, taking it from the system allocator if this is the first
+    /// call, or `None` if that fails.
+    fn region(&self) -> Option<*mut u8> {
+        let start = self.region.load(Ordering::Acquire);
+        if !start.is_null() {
+            return Some(start);
+        }
+
+        let layout = self.region_layout()?;
+        let fresh = unsafe { System.alloc(layout) };
+        if fresh.is_null() {
+            return None;
+        }
+        // Another thread may have won the race, in which case its region is used
+        match self.region.compare_exchange(
+            ptr::null_mut(),
+            fresh,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => Some(fresh),
+            Err(winner) => {
+                unsafe { System.dealloc(fresh, layout) };
+                Some(winner)
+            }
+        }
+    }
+
+    fn region_layout(&self) -> Option<Layout> {
+        Layout::from_size_align(self.bump.capacity(), REGION_ALIGN)
+            .ok()
+            .filter(|layout| layout.size() > 0)
+    }
+}
+
+/// Frees the region of an allocator that is not a `static`, such as one made in a test.
+/// Blocks from the fallback are left to their owners.
+impl Drop for GlobalBumpAllocator {
+    fn drop(&mut self) {
+        let start = *self.region.get_mut();
+        if start.is_null() {
+            return;
+        }
+        // A region is only taken when its layout is valid
+        if let Some(layout) = self.region_layout() {
+            unsafe { System.dealloc(start, layout) };
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for GlobalBumpAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.align() <= REGION_ALIGN {
+            if let Some(start) = self.region() {
+                if let Ok(offset) = self.bump.alloc(layout.size(), layout.align()) {
+                    return start.add(offset);
+                }
+            }
+        }
+        self.fallbacks.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // Blocks in the region are only reclaimed when the process exits
+        if !self.contains(ptr) {
+            System.dealloc(ptr, layout);
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if !self.contains(ptr) {
+            return System.realloc(ptr, layout, new_size);
+        }
+        if new_size <= layout.size() {
+            // Shrinking in place leaves the tail unused
+            return ptr;
+        }
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size());
+        }
+        new_ptr
+    }
+}
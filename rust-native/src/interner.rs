@@ -0,0 +1,142 @@
+//! String interner that stores each distinct string once in a [`BumpAllocator`].
+
+use std::collections::HashMap;
+use std::{fmt, slice, str};
+
+use crate::allocator::{AllocError, BumpAllocator};
+
+/// Index of a string interned by a [`BumpInterner`]. Symbols are handed out in order,
+/// starting at 0, and only mean something to the interner that made them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+/// Why [`BumpInterner::try_intern`] could not intern a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InternError {
+    /// The allocator could not hold the string.
+    Alloc(AllocError),
+    /// Every `u32` is already a symbol.
+    TooManySymbols,
+}
+
+impl From<AllocError> for InternError {
+    fn from(error: AllocError) -> Self {
+        InternError::Alloc(error)
+    }
+}
+
+impl fmt::Display for InternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InternError::Alloc(error) => error.fmt(f),
+            InternError::TooManySymbols => f.write_str("too many interned strings"),
+        }
+    }
+}
+
+impl std::error::Error for InternError {}
+
+/// Deduplicates strings by copying each new one into a [`BumpAllocator`] and indexing
+/// it in a hash map. The interner borrows the allocator, so the strings it returns
+/// stay valid until the allocator is reset, even after the interner is dropped:
+///
+/// ```compile_fail
+/// use optimize_bump_allocation::{allocator::BumpAllocator, interner::BumpInterner};
+///
+/// let mut bump = BumpAllocator::new(1024);
+/// let mut interner = BumpInterner::new(&bump);
+/// let name = interner.intern_str("name");
+/// bump.reset(); // error: `bump` is still borrowed by `name`
+/// assert_eq!(name, "name");
+/// ```
+///
+/// The index and the symbol table live on the system heap, only the string bytes are
+/// bump allocated.
+pub struct BumpInterner<'a> {
+    allocator: &'a BumpAllocator,
+    symbols: HashMap<&'a str, Symbol>,
+    strings: Vec<&'a str>,
+}
+
+impl<'a> BumpInterner<'a> {
+    pub fn new(allocator: &'a BumpAllocator) -> Self {
+        BumpInterner {
+            allocator,
+            symbols: HashMap::new(),
+            strings: Vec::new(),
+        }
+    }
+
+    /// Symbol of `s`, copying it into the allocator if it was not interned yet.
+    ///
+    /// # Panics
+    ///
+    /// If the allocator cannot hold `s` or every `u32` is already a symbol.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        self.try_intern(s).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like [`intern`](Self::intern), but returns an error instead of panicking.
+    /// Nothing is interned on error.
+    pub fn try_intern(&mut self, s: &str) -> Result<Symbol, InternError> {
+        if let Some(&symbol) = self.symbols.get(s) {
+            return Ok(symbol);
+        }
+        // Checked first, so nothing is allocated for a string that gets no symbol
+        let index = u32::try_from(self.strings.len()).map_err(|_| InternError::TooManySymbols)?;
+        let symbol = Symbol(index);
+        // The block borrows the allocator for 'a through `self.allocator`, which cannot
+        // be reset while that borrow lives
+        let stored: &'a str = unsafe {
+            let ptr = self.allocator.try_allocate(s.len(), 1)?.as_ptr();
+            ptr.copy_from_nonoverlapping(s.as_ptr(), s.len());
+            str::from_utf8_unchecked(slice::from_raw_parts(ptr, s.len()))
+        };
+        self.symbols.insert(stored, symbol);
+        self.strings.push(stored);
+        Ok(symbol)
+    }
+
+    /// Interned copy of `s`, shared by every caller that interns an equal string.
+    pub fn intern_str(&mut self, s: &str) -> &'a str {
+        let symbol = self.intern(s);
+        self.strings[symbol.0 as usize]
+    }
+
+    /// Symbol of `s` if it has been interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.symbols.get(s).copied()
+    }
+
+    /// String of `symbol`.
+    ///
+    /// # Panics
+    ///
+    /// If `symbol` was not made by this interner.
+    pub fn resolve(&self, symbol: Symbol) -> &'a str {
+        self.strings[symbol.0 as usize]
+    }
+
+    /// Number of distinct strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Interned strings in symbol order.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &'a str)> + '_ {
+        self.strings
+            .iter()
+            .enumerate()
+            .map(|(index, &s)| (Symbol(index as u32), s))
+    }
+}
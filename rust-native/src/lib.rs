@@ -0,0 +1,16 @@
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+
+pub mod allocator;
+pub mod backend;
+pub mod chunked_allocator;
+pub mod collections;
+pub mod counting_allocator;
+pub mod global_bump;
+pub mod interner;
+pub mod measure_tests;
+#[cfg(target_os = "linux")]
+pub mod mmap;
+pub mod multi_thread_allocator;
+pub mod multi_thread_benchmark;
+pub mod stress_benchmark;
+pub mod thread_local_arena;
@@ -1,17 +1,288 @@
-pub mod allocator;
-pub mod measure_tests;
-pub mod multi_thread_allocator;
+use clap::builder::{PossibleValuesParser, RangedU64ValueParser};
+use clap::{Args, Command, FromArgMatches, Parser, Subcommand};
+use optimize_bump_allocation::allocator;
+use optimize_bump_allocation::backend::Backend;
+#[cfg(feature = "counting")]
+use optimize_bump_allocation::counting_allocator::{self, CountingAlloc};
+#[cfg(feature = "global-bump")]
+use optimize_bump_allocation::global_bump::GlobalBumpAllocator;
+#[cfg(feature = "nightly")]
+use optimize_bump_allocation::measure_tests::measure_collections;
+#[cfg(target_os = "linux")]
+use optimize_bump_allocation::measure_tests::measure_first_touch;
+use optimize_bump_allocation::measure_tests::{
+    measure_allocation_and_reset_patterns, measure_chunked_allocator, measure_interner,
+    measure_scopes, measure_varying_allocation_sizes, with_allocation_counters, WORKLOADS,
+};
+use optimize_bump_allocation::multi_thread_benchmark::measure_multithreaded_allocators;
+use optimize_bump_allocation::stress_benchmark::measure_stress_allocations;
+#[cfg(all(feature = "counting", not(feature = "global-bump")))]
+use std::alloc::System;
 
-use measure_tests::{measure_allocation_and_reset_patterns, measure_varying_allocation_sizes};
+/// Size of the region behind every `Box`, `Vec` and `String` in the process.
+#[cfg(feature = "global-bump")]
+const GLOBAL_BUMP_CAPACITY: usize = 256 * 1024 * 1024;
 
-pub fn align_up(address: usize, align: usize) -> usize {
-    (address + align - 1) & !(align - 1)
+/// Counts system allocations so the benchmarks can report them.
+#[cfg(all(feature = "counting", not(feature = "global-bump")))]
+#[global_allocator]
+static GLOBAL: CountingAlloc<System> = CountingAlloc::new(System);
+
+#[cfg(all(feature = "counting", feature = "global-bump"))]
+#[global_allocator]
+static GLOBAL: CountingAlloc<GlobalBumpAllocator> =
+    CountingAlloc::new(GlobalBumpAllocator::new(GLOBAL_BUMP_CAPACITY));
+
+#[cfg(all(feature = "global-bump", not(feature = "counting")))]
+#[global_allocator]
+static GLOBAL: GlobalBumpAllocator = GlobalBumpAllocator::new(GLOBAL_BUMP_CAPACITY);
+
+/// Compare the bump allocators in this crate with the system allocator.
+///
+/// Without a subcommand every benchmark runs with its default settings.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Allocators to compare; each benchmark skips the ones it does not support
+    #[arg(
+        long,
+        short,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = Backend::ALL
+    )]
+    allocators: Vec<Backend>,
+
+    #[command(subcommand)]
+    benchmark: Option<Benchmark>,
+}
+
+#[derive(Subcommand)]
+enum Benchmark {
+    /// Allocate values of different sizes and alignments, as one table
+    Sizes(SizesArgs),
+    /// Fill the bump allocator and reset it, several times over
+    Reset(ResetArgs),
+    /// Compare nested scopes with a full reset per request
+    Scopes(ScopesArgs),
+    /// Intern strings with the bump interner and a system HashMap
+    Interner(InternerArgs),
+    /// Allocate until the allocators run out of memory
+    Stress(StressArgs),
+    /// Compare a growing chunked allocator with a fixed buffer
+    Chunked(ChunkedArgs),
+    /// Compare heap and mmap buffers by the cost of touching their pages
+    #[cfg(target_os = "linux")]
+    FirstTouch(FirstTouchArgs),
+    /// Allocate from several threads at once
+    Threads(ThreadsArgs),
+    /// Push to `Vec` and `Box` values backed by the bump allocator
+    #[cfg(feature = "nightly")]
+    Collections(CollectionsArgs),
+    /// Allocate u64s with the bump allocator and the system allocator
+    Baseline(BaselineArgs),
+    /// Run every benchmark except the baseline with its default settings
+    All,
+}
+
+#[derive(Args)]
+struct SizesArgs {
+    /// Value types to allocate [default: all]
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = PossibleValuesParser::new(WORKLOADS.iter().map(|workload| workload.name))
+    )]
+    types: Vec<String>,
+    /// Allocations per value type
+    #[arg(long, short = 'n', default_value_t = 100_000)]
+    count: usize,
+}
+
+#[derive(Args)]
+struct ResetArgs {
+    /// Allocations between two resets
+    #[arg(long, short = 'n', default_value_t = 500_000, value_parser = non_zero())]
+    count: usize,
+    /// Number of fill-and-reset rounds
+    #[arg(long, short, default_value_t = 10)]
+    iterations: usize,
+}
+
+#[derive(Args)]
+struct ScopesArgs {
+    /// Number of requests
+    #[arg(long, short = 'n', default_value_t = 1_000)]
+    count: usize,
+    /// Phases per request
+    #[arg(long, default_value_t = 4)]
+    phases: usize,
+    /// u64 temporaries allocated in each phase
+    #[arg(long, default_value_t = 1_000)]
+    allocations_per_phase: usize,
+}
+
+#[derive(Args)]
+struct InternerArgs {
+    /// Number of strings to intern
+    #[arg(long, short = 'n', default_value_t = 1_000_000)]
+    count: usize,
+    /// Number of different strings among them
+    #[arg(long, default_value_t = 10_000)]
+    distinct: usize,
+}
+
+#[derive(Args)]
+struct StressArgs {
+    /// Bytes each allocator may use before it fails
+    #[arg(long, default_value_t = 10 * 1024 * 1024, value_parser = non_zero())]
+    budget: usize,
+}
+
+#[derive(Args)]
+struct ChunkedArgs {
+    /// Number of u64s to allocate
+    #[arg(long, short = 'n', default_value_t = 500_000, value_parser = non_zero())]
+    count: usize,
+    /// Size in bytes of the chunked allocator's first chunk
+    #[arg(long, default_value_t = 4 * 1024)]
+    first_chunk_size: usize,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Args)]
+struct FirstTouchArgs {
+    /// Buffer size in bytes
+    #[arg(long, default_value_t = 160 * 1024 * 1024, value_parser = non_zero())]
+    capacity: usize,
+}
+
+#[derive(Args)]
+struct ThreadsArgs {
+    /// Thread counts to measure, one run each
+    #[arg(long, short, value_delimiter = ',', default_values_t = [1, 2, 4, 8, 16])]
+    threads: Vec<usize>,
+    /// Allocations per thread
+    #[arg(long, short = 'n', default_value_t = 100_000, value_parser = non_zero())]
+    count: usize,
+}
+
+#[cfg(feature = "nightly")]
+#[derive(Args)]
+struct CollectionsArgs {
+    /// Elements per collection
+    #[arg(long, short = 'n', default_value_t = 500_000)]
+    count: usize,
+}
+
+#[derive(Args)]
+struct BaselineArgs {
+    /// Number of u64s to allocate
+    #[arg(long, short = 'n', default_value_t = 10_000_000)]
+    count: usize,
+}
+
+/// Parser for counts and sizes that must be at least 1. A buffer of 0 bytes would be
+/// useless to benchmark.
+fn non_zero() -> RangedU64ValueParser<usize> {
+    RangedU64ValueParser::new().range(1..)
+}
+
+/// `T` with every option at its default value.
+fn defaults<T: Args + FromArgMatches>() -> T {
+    let matches = T::augment_args(Command::new("defaults")).get_matches_from(["defaults"]);
+    T::from_arg_matches(&matches).expect("default arguments are valid")
+}
+
+impl Benchmark {
+    /// Run the benchmark, or each of them for `All`, and print the system allocator
+    /// calls it made.
+    fn run(self, backends: &[Backend]) {
+        match self {
+            Benchmark::All => {
+                // Measure varying allocation sizes
+                Benchmark::Sizes(defaults()).run(backends);
+
+                // Measure allocation and reset patterns
+                Benchmark::Reset(defaults()).run(backends);
+
+                // Measure nested scopes against resetting the whole allocator
+                Benchmark::Scopes(defaults()).run(backends);
+
+                // Measure interning strings
+                Benchmark::Interner(defaults()).run(backends);
+
+                // Measure allocating until the bump allocator and a limited system allocator run out
+                Benchmark::Stress(defaults()).run(backends);
+
+                // Measure a chunked allocator that grows on demand
+                Benchmark::Chunked(defaults()).run(backends);
+
+                // Measure the page fault cost of heap and mmap buffers
+                #[cfg(target_os = "linux")]
+                Benchmark::FirstTouch(defaults()).run(backends);
+
+                // Measure thread-safe allocators under contention
+                Benchmark::Threads(defaults()).run(backends);
+
+                // Measure standard collections backed by the bump allocator
+                #[cfg(feature = "nightly")]
+                Benchmark::Collections(defaults()).run(backends);
+            }
+            benchmark => with_allocation_counters(|| benchmark.measure(backends)),
+        }
+    }
+
+    fn measure(self, backends: &[Backend]) {
+        match self {
+            Benchmark::Sizes(args) => {
+                measure_varying_allocation_sizes(&args.types, args.count, backends)
+            }
+            Benchmark::Reset(args) => {
+                measure_allocation_and_reset_patterns(args.count, args.iterations, backends)
+            }
+            Benchmark::Scopes(args) => measure_scopes(
+                args.count,
+                args.phases,
+                args.allocations_per_phase,
+                backends,
+            ),
+            Benchmark::Interner(args) => measure_interner(args.count, args.distinct, backends),
+            Benchmark::Stress(args) => measure_stress_allocations(args.budget, backends),
+            Benchmark::Chunked(args) => {
+                measure_chunked_allocator(args.count, args.first_chunk_size, backends)
+            }
+            #[cfg(target_os = "linux")]
+            Benchmark::FirstTouch(args) => measure_first_touch(args.capacity, backends),
+            Benchmark::Threads(args) => {
+                measure_multithreaded_allocators(&args.threads, args.count, backends)
+            }
+            #[cfg(feature = "nightly")]
+            Benchmark::Collections(args) => measure_collections(args.count, backends),
+            Benchmark::Baseline(args) => allocator::run(args.count, backends),
+            Benchmark::All => unreachable!("`run` expands `All`"),
+        }
+    }
 }
 
 fn main() {
-    // Measure varying allocation sizes
-    measure_varying_allocation_sizes();
+    #[cfg(feature = "counting")]
+    counting_allocator::register(&GLOBAL);
+
+    let cli = Cli::parse();
+    cli.benchmark.unwrap_or(Benchmark::All).run(&cli.allocators);
 
-    // Measure allocation and reset patterns
-    measure_allocation_and_reset_patterns();
+    #[cfg(feature = "global-bump")]
+    {
+        #[cfg(feature = "counting")]
+        let buffer = GLOBAL.inner();
+        #[cfg(not(feature = "counting"))]
+        let buffer = &GLOBAL;
+        println!(
+            "\nGlobal bump buffer: {} bytes used, {} allocations fell back to the system allocator",
+            buffer.used(),
+            buffer.fallbacks()
+        );
+    }
 }
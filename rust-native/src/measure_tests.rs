@@ -1,453 +1,675 @@
+use bump_core::BumpPosition;
 use colored::Colorize;
-use std::{mem, ptr, sync::Arc, thread, time::Instant};
-
-use crate::{allocator::BumpAllocator, multi_thread_allocator::ThreadSafeBumpAllocator};
+use std::collections::HashMap;
+use std::mem;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{
+    allocator::{BumpAllocator, Frame, ResetPolicy},
+    backend::Backend,
+    chunked_allocator::ChunkedBumpAllocator,
+    counting_allocator,
+    interner::BumpInterner,
+    multi_thread_allocator::SharedBumpAllocator,
+    thread_local_arena::ArenaPool,
+};
 
 /// Define a large data to test allocations of larger sizes.
 #[derive(Debug)]
+#[allow(dead_code)] // Only the size matters
 struct LargeData {
     data: [u64; 128], // 1024 bytes
 }
 
-/// Test allocating varying sizes: u8, u64, and LargeStruct.
-pub fn measure_varying_allocation_sizes() {
-    println!("\n /////////////// Measure varying allocation sizes ////////////// \n");
+impl Default for LargeData {
+    fn default() -> Self {
+        LargeData { data: [0; 128] }
+    }
+}
 
-    let num_allocations = 100_000;
+/// Size that is not a multiple of any alignment above 1.
+#[derive(Debug, Default)]
+#[allow(dead_code)]
+struct OddSize([u8; 13]);
+
+/// Small value aligned to a cache line, so every allocation needs padding.
+#[derive(Debug, Default)]
+#[allow(dead_code)]
+#[repr(align(64))]
+struct OverAligned(u8);
+
+/// Zero-sized value, which needs no memory at all.
+#[derive(Debug, Default)]
+struct ZeroSized;
+
+/// One group of rows in the varying sizes table: a value type and how to measure it.
+pub struct Workload {
+    pub name: &'static str,
+    pub size: usize,
+    pub align: usize,
+    measure: fn(Backend, usize) -> Measurement,
+}
 
-    // Define different types to allocate
-    let types = vec!["u8", "u64", "LargeData"];
+const fn workload<T: Default>(name: &'static str) -> Workload {
+    Workload {
+        name,
+        size: mem::size_of::<T>(),
+        align: mem::align_of::<T>(),
+        measure: measure_allocations::<T>,
+    }
+}
 
-    for typ in types {
-        match typ {
-            "u8" => {
-                println!("{}", format!("Measure u8...").purple().italic());
-                // Bump Allocator
-                let size = mem::size_of::<u8>();
-                let align = mem::align_of::<u8>();
-                let buffer_size = num_allocations * size * 2; // Extra space for alignment
-                let allocator = BumpAllocator::new(buffer_size);
-                let mut allocations: Vec<*mut u8> = Vec::with_capacity(num_allocations);
+/// Value types measured by [`measure_varying_allocation_sizes`].
+pub const WORKLOADS: &[Workload] = &[
+    workload::<u8>("u8"),
+    workload::<u64>("u64"),
+    workload::<LargeData>("large-data"),
+    workload::<OddSize>("odd-size"),
+    workload::<OverAligned>("over-aligned"),
+    workload::<ZeroSized>("zero-sized"),
+];
+
+/// Time and memory taken by one backend for one workload.
+struct Measurement {
+    duration: Duration,
+    /// Bytes taken from the allocator, alignment padding included, or `None` if the
+    /// backend cannot report it.
+    consumed: Option<usize>,
+}
 
-                let start = Instant::now();
+/// Time `num_allocations` allocations of `T::default()` with `backend` and measure the
+/// memory they consumed. Each backend is one line here, pointing at a function that
+/// takes the number of allocations.
+fn measure_allocations<T: Default>(backend: Backend, num_allocations: usize) -> Measurement {
+    let measure: fn(usize) -> Measurement = match backend {
+        Backend::Bump => measure_bump::<T>,
+        Backend::Chunked => measure_chunked::<T>,
+        Backend::Mutex => measure_shared::<T, Mutex<usize>>,
+        Backend::Atomic => measure_shared::<T, AtomicUsize>,
+        Backend::Arenas => measure_arenas::<T>,
+        Backend::System => measure_system::<T>,
+    };
+    measure(num_allocations)
+}
 
-                for i in 0..num_allocations {
-                    let ptr = allocator.allocate(size, align) as *mut u8;
-                    unsafe {
-                        ptr::write(ptr, i as u8);
-                    }
-                    allocations.push(ptr);
-                }
+/// Room for `num_allocations` values of `T` plus padding before the first one.
+fn capacity_for<T>(num_allocations: usize) -> usize {
+    num_allocations * mem::size_of::<T>() + mem::align_of::<T>()
+}
 
-                let duration = start.elapsed();
-                println!(
-                    "{}: allocated {} u8s in {}",
-                    format!("Bump Allocator").blue(),
-                    num_allocations.to_string().green(),
-                    format!("{:?}", duration).bold().green()
-                );
+fn measure_bump<T: Default>(num_allocations: usize) -> Measurement {
+    let allocator = BumpAllocator::new(capacity_for::<T>(num_allocations));
+    Measurement {
+        duration: time_allocations(num_allocations, || allocator.alloc(T::default())),
+        consumed: Some(allocator.used()),
+    }
+}
 
-                // Standard Allocator
-                let mut std_allocations: Vec<*mut u8> = Vec::with_capacity(num_allocations);
-                let start = Instant::now();
+fn measure_chunked<T: Default>(num_allocations: usize) -> Measurement {
+    let allocator = ChunkedBumpAllocator::new(4 * 1024);
+    Measurement {
+        duration: time_allocations(num_allocations, || allocator.alloc(T::default())),
+        consumed: Some(allocator.used()),
+    }
+}
 
-                for i in 0..num_allocations {
-                    let boxed = Box::new(i as u8);
-                    std_allocations.push(Box::into_raw(boxed));
-                }
+/// A [`SharedBumpAllocator`] over position `P`, through a single handle.
+fn measure_shared<T: Default, P: BumpPosition + Default>(num_allocations: usize) -> Measurement {
+    let allocator = SharedBumpAllocator::<P>::new(capacity_for::<T>(num_allocations));
+    let handle = allocator.handle();
+    let duration = time_allocations(num_allocations, || handle.alloc(T::default()));
+    drop(handle);
+    Measurement {
+        duration,
+        consumed: Some(allocator.used()),
+    }
+}
 
-                let duration = start.elapsed();
-                println!(
-                    "{}: Allocated {} u8s in {}",
-                    format!("Standard Allocator").blue(),
-                    num_allocations.to_string().green(),
-                    format!("{:?}", duration).bold().green()
-                );
+fn measure_arenas<T: Default>(num_allocations: usize) -> Measurement {
+    let pool = ArenaPool::new(capacity_for::<T>(num_allocations));
+    let arena = pool.arena();
+    Measurement {
+        duration: time_allocations(num_allocations, || arena.alloc(T::default())),
+        consumed: None,
+    }
+}
 
-                // Cleanup
-                for ptr in std_allocations {
-                    unsafe {
-                        let _ = Box::from_raw(ptr);
-                    }
-                }
+fn measure_system<T: Default>(num_allocations: usize) -> Measurement {
+    // Nothing is counted unless the binary installed and registered `CountingAlloc`
+    let Some(counters) = counting_allocator::registered() else {
+        return Measurement {
+            duration: time_allocations(num_allocations, || Box::new(T::default())),
+            consumed: None,
+        };
+    };
+    let before = counters.stats().live_bytes;
+    let mut values = Vec::with_capacity(num_allocations);
+    let start = Instant::now();
+    for _ in 0..num_allocations {
+        values.push(Box::new(T::default()));
+    }
+    let duration = start.elapsed();
+    // The vector holding the boxes is counted too
+    let vector = mem::size_of::<Box<T>>() * num_allocations;
+    Measurement {
+        duration,
+        consumed: Some(counters.stats().live_bytes - before - vector),
+    }
+}
 
-                println!("\n");
-            }
-            "u64" => {
-                println!("{}", format!("Measure u64...").purple().italic());
-                // Bump Allocator
-                let size = mem::size_of::<u64>();
-                let align = mem::align_of::<u64>();
-                let buffer_size = num_allocations * size * 2;
-                let allocator = BumpAllocator::new(buffer_size);
-                let mut allocations: Vec<*mut u64> = Vec::with_capacity(num_allocations);
+/// Time `num_allocations` calls to `alloc`, keeping every value until the clock stops.
+fn time_allocations<R>(num_allocations: usize, mut alloc: impl FnMut() -> R) -> Duration {
+    let mut values = Vec::with_capacity(num_allocations);
+    let start = Instant::now();
+    for _ in 0..num_allocations {
+        values.push(alloc());
+    }
+    start.elapsed()
+}
 
-                let start = Instant::now();
+/// Allocate `num_allocations` values of each workload named in `types` (all of them
+/// if empty) with each of `backends`, and print one row per workload and backend with
+/// the time taken, the bytes requested and consumed, and the difference lost to
+/// alignment.
+pub fn measure_varying_allocation_sizes(
+    types: &[String],
+    num_allocations: usize,
+    backends: &[Backend],
+) {
+    println!("\n /////////////// Measure varying allocation sizes ////////////// \n");
 
-                for i in 0..num_allocations {
-                    let ptr = allocator.allocate(size, align) as *mut u64;
-                    unsafe {
-                        ptr::write(ptr, i as u64);
-                    }
-                    allocations.push(ptr);
-                }
+    let workloads = WORKLOADS
+        .iter()
+        .filter(|workload| types.is_empty() || types.iter().any(|name| name == workload.name));
+
+    println!(
+        "{:<14}{:>6}{:>6}  {:<9}{:>14}{:>12}{:>12}{:>12}",
+        "type", "size", "align", "backend", "time", "requested", "consumed", "waste"
+    );
 
-                let duration = start.elapsed();
-                println!(
-                    "{}: Allocated {} u64s in {}",
-                    format!("Bump Allocator").blue(),
-                    num_allocations.to_string().green(),
-                    format!("{:?}", duration).bold().green()
+    for workload in workloads {
+        let requested = workload.size * num_allocations;
+        for (i, &backend) in backends.iter().enumerate() {
+            if i == 0 {
+                print!(
+                    "{}{:>6}{:>6}",
+                    format!("{:<14}", workload.name).purple(),
+                    workload.size,
+                    workload.align
                 );
+            } else {
+                print!("{:26}", "");
+            }
+
+            let measurement = (workload.measure)(backend, num_allocations);
+            let (consumed, waste) = match measurement.consumed {
+                Some(consumed) => (
+                    consumed.to_string(),
+                    consumed.saturating_sub(requested).to_string(),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            println!(
+                "  {:<9}{}{:>12}{:>12}{}",
+                format!("{:?}", backend),
+                format!("{:>14?}", measurement.duration).green(),
+                requested,
+                consumed,
+                format!("{:>12}", waste).yellow()
+            );
+        }
+    }
+    println!(
+        "\n{} allocations per row, values kept alive until timing stops",
+        num_allocations.to_string().bold()
+    );
+}
+
+/// Run the benchmark `run` and print the global allocator calls it made, if the binary
+/// counts them with [`CountingAlloc`](counting_allocator::CountingAlloc). That is the
+/// system allocator, or the global bump allocator with the `global-bump` feature.
+pub fn with_allocation_counters(run: impl FnOnce()) {
+    let Some(counters) = counting_allocator::registered() else {
+        return run();
+    };
+    counters.reset_peak();
+    let before = counters.stats();
+    run();
+    let stats = counters.stats().since(before);
+    println!("\n{} {}", "Global allocator:".blue(), stats);
+}
+
+/// Test allocating and resetting the bump allocator multiple times.
+pub fn measure_allocation_and_reset_patterns(
+    num_allocations: usize,
+    iterations: usize,
+    backends: &[Backend],
+) {
+    println!("\n /////////////// Measure allocation and reset patterns ////////////// \n");
 
-                // Standard Allocator
-                let mut std_allocations: Vec<*mut u64> = Vec::with_capacity(num_allocations);
+    if backends.contains(&Backend::Bump) {
+        // Bump Allocator, once per reset policy
+        println!("==== MEASURE BUMP ALLOCATOR ====");
+        let size = mem::size_of::<u64>();
+        let buffer_size = num_allocations * size * 2;
+        let policies = [
+            ResetPolicy::Plain,
+            ResetPolicy::Zero,
+            ResetPolicy::Poison,
+            // Keep the first quarter of the buffer, release the rest of what was used
+            ResetPolicy::Release {
+                threshold: buffer_size / 4,
+            },
+        ];
+
+        for policy in policies {
+            let mut allocator = BumpAllocator::new(buffer_size);
+            allocator.set_reset_policy(policy);
+            let (mut fills, mut resets) = (Duration::ZERO, Duration::ZERO);
+
+            for _ in 0..iterations {
                 let start = Instant::now();
 
+                // Allocations borrow the allocator, so they must be gone before the reset below
+                let mut allocations: Vec<&mut u64> = Vec::with_capacity(num_allocations);
                 for i in 0..num_allocations {
-                    let boxed = Box::new(i as u64);
-                    std_allocations.push(Box::into_raw(boxed));
+                    allocations.push(allocator.alloc(i as u64));
                 }
+                fills += start.elapsed();
+                drop(allocations);
 
-                let duration = start.elapsed();
-                println!(
-                    "{}: Allocated {} u64s in {}",
-                    format!("Standard Allocator").blue(),
-                    num_allocations.to_string().green(),
-                    format!("{:?}", duration).bold().green()
-                );
-
-                // Cleanup
-                for ptr in std_allocations {
-                    unsafe {
-                        let _ = Box::from_raw(ptr);
-                    }
-                }
+                let start = Instant::now();
+                allocator.reset();
+                resets += start.elapsed();
+            }
 
-                println!("\n");
+            let rounds = iterations.max(1) as u32;
+            println!(
+                "{}: {} iterations in {}, fill {} and reset {} on average, high-water mark {} bytes",
+                format!("{:?}", policy).purple(),
+                iterations.to_string().bold().green(),
+                format!("{:?}", fills + resets).bold().green(),
+                format!("{:?}", fills / rounds).green(),
+                format!("{:?}", resets / rounds).yellow(),
+                allocator.high_water_mark()
+            );
+        }
+        println!(
+            "{} u64s per fill in a {} byte buffer{}",
+            num_allocations.to_string().bold(),
+            buffer_size,
+            if cfg!(debug_assertions) {
+                ""
+            } else {
+                "; Poison only writes in debug builds"
             }
-            "LargeData" => {
-                println!("{}", format!("Measure LargeData...").purple().italic());
-                
-                // Bump Allocator
-                let size = mem::size_of::<LargeData>();
-                let align = mem::align_of::<LargeData>();
-                let buffer_size = num_allocations * size * 2;
-                let allocator = BumpAllocator::new(buffer_size);
-                let mut allocations: Vec<*mut LargeData> = Vec::with_capacity(num_allocations);
+        );
+    }
 
-                let start = Instant::now();
+    println!("\n");
+    if backends.contains(&Backend::System) {
+        // Standard Allocator
+        println!("==== MEASURE STANADARD ALLOCATOR ====");
+        let mut std_allocations: Vec<*mut u64> = Vec::with_capacity(num_allocations * iterations);
 
-                for _ in 0..num_allocations {
-                    let ptr = allocator.allocate(size, align) as *mut LargeData;
-                    unsafe {
-                        ptr::write(ptr, LargeData { data: [0; 128] });
-                    }
-                    allocations.push(ptr);
-                }
+        let total_start = Instant::now();
 
-                let duration = start.elapsed();
-                println!(
-                    "Bump Allocator: Allocated {} LargeData in {}",
-                    num_allocations.to_string().green(),
-                    format!("{:?}", duration).bold().green()
-                );
+        for iter in 0..iterations {
+            let start = Instant::now();
 
-                // Standard Allocator
-                let mut std_allocations: Vec<*mut LargeData> = Vec::with_capacity(num_allocations);
-                let start = Instant::now();
+            for i in 0..num_allocations {
+                let boxed = Box::new(i as u64);
+                std_allocations.push(Box::into_raw(boxed));
+            }
 
-                for _ in 0..num_allocations {
-                    let boxed = Box::new(LargeData { data: [0; 128] });
-                    std_allocations.push(Box::into_raw(boxed));
-                }
+            let duration = start.elapsed();
+            println!(
+                "Iteration {} - Allocated {} u64s in {:?}",
+                iter + 1,
+                num_allocations,
+                duration
+            );
+        }
 
-                let duration = start.elapsed();
-                println!(
-                    "Standard Allocator: Allocated {} LargeData in {}",
-                    num_allocations.to_string().green(),
-                    format!("{:?}", duration).bold().green()
-                );
+        let total_duration = total_start.elapsed();
+        println!(
+            "Total time for {} with {} iterations: {}",
+            "STANDARD ALLOCATOR".purple(),
+            iterations.to_string().bold().green(),
+            format!("{:?}", total_duration).bold().green()
+        );
 
-                // Cleanup
-                for ptr in std_allocations {
-                    unsafe {
-                        let _ = Box::from_raw(ptr);
-                    }
-                }
+        // Cleanup
+        for ptr in std_allocations {
+            unsafe {
+                let _ = Box::from_raw(ptr);
             }
-            _ => {}
         }
     }
 }
 
-/// Test allocating and resetting the bump allocator multiple times.
-pub fn measure_allocation_and_reset_patterns() {
-    println!("\n /////////////// Measure allocation and reset patterns ////////////// \n");
-    let num_allocations = 500_000;
-    let iterations = 10;
-
-    // Bump Allocator
-    println!("==== MEASURE BUMP ALLOCATOR ====");
-    let size = mem::size_of::<u64>();
-    let align = mem::align_of::<u64>();
-    let buffer_size = num_allocations * size * 2;
-    let allocator = BumpAllocator::new(buffer_size);
-    let mut allocations: Vec<*mut u64> = Vec::with_capacity(num_allocations);
+/// Compare heap and mmap buffers of `capacity` bytes filled with u64s: the time to
+/// create the allocator, to fill it while its pages are first touched, and to fill it
+/// again after a reset. Only the Bump backend is run.
+#[cfg(target_os = "linux")]
+pub fn measure_first_touch(capacity: usize, backends: &[Backend]) {
+    use crate::mmap::MmapOptions;
 
-    let total_start = Instant::now();
+    println!("\n /////////////// Measure first-touch cost ////////////// \n");
+    if !backends.contains(&Backend::Bump) {
+        return;
+    }
 
-    for iter in 0..iterations {
+    let buffers = [
+        ("Heap buffer", None),
+        ("Mmap buffer", Some(MmapOptions::default())),
+        (
+            "Mmap buffer, huge pages",
+            Some(MmapOptions { huge_pages: true }),
+        ),
+    ];
+    let num_allocations = capacity / mem::size_of::<u64>();
+
+    for (name, mmap) in buffers {
         let start = Instant::now();
+        let mut allocator = match mmap {
+            None => BumpAllocator::new(capacity),
+            Some(options) => BumpAllocator::with_mmap(capacity, options),
+        };
+        let setup = start.elapsed();
 
-        for i in 0..num_allocations {
-            let ptr = allocator.allocate(size, align) as *mut u64;
-            unsafe {
-                ptr::write(ptr, i as u64);
-            }
-            allocations.push(ptr);
-        }
+        let first_fill = fill_u64s(&allocator, num_allocations);
+        allocator.reset();
+        let refill = fill_u64s(&allocator, num_allocations);
 
-        let duration = start.elapsed();
         println!(
-            "Iteration {} - Allocated {} u64s in {:?}",
-            iter + 1,
-            num_allocations,
-            duration
+            "{}: setup {}, first fill {}, refill after reset {}",
+            name.blue(),
+            format!("{:?}", setup).green(),
+            format!("{:?}", first_fill).bold().green(),
+            format!("{:?}", refill).green()
         );
+    }
+    println!(
+        "\n{} bytes per buffer; reset discards mmap pages, so they are touched again",
+        capacity.to_string().bold()
+    );
+}
 
+#[cfg(target_os = "linux")]
+fn fill_u64s(allocator: &BumpAllocator, num_allocations: usize) -> Duration {
+    let start = Instant::now();
+    for i in 0..num_allocations {
+        std::hint::black_box(allocator.alloc(i as u64));
+    }
+    start.elapsed()
+}
+
+/// Compare nested scopes with a full reset for `num_requests` requests, each made of
+/// `phases` phases that allocate `allocations_per_phase` u64 temporaries. Scopes free
+/// every phase's temporaries when it ends; the reset frees the whole request at once.
+/// Only the Bump backend is run.
+pub fn measure_scopes(
+    num_requests: usize,
+    phases: usize,
+    allocations_per_phase: usize,
+    backends: &[Backend],
+) {
+    println!("\n /////////////// Measure scopes and reset ////////////// \n");
+    if !backends.contains(&Backend::Bump) {
+        return;
+    }
+    let phase_size = allocations_per_phase * mem::size_of::<u64>();
+
+    // Room for one phase at a time, plus the request's result
+    let mut allocator = BumpAllocator::new(phase_size + mem::size_of::<u64>());
+    let start = Instant::now();
+    for request in 0..num_requests {
+        allocator.scope(|frame| {
+            let mut total = 0;
+            for _ in 0..phases {
+                total += frame.scope(|phase| fill_phase(phase, allocations_per_phase));
+            }
+            frame.alloc(total + request as u64);
+        });
+    }
+    print_scope_result("Nested scopes", start.elapsed(), &allocator);
+
+    // Room for every phase of a request, freed together
+    let mut allocator = BumpAllocator::new(phases * phase_size + mem::size_of::<u64>());
+    let start = Instant::now();
+    for request in 0..num_requests {
+        let mut total = 0;
+        for _ in 0..phases {
+            total += (0..allocations_per_phase)
+                .map(|i| *allocator.alloc(i as u64))
+                .sum::<u64>();
+        }
+        allocator.alloc(total + request as u64);
         allocator.reset();
     }
+    print_scope_result("Reset per request", start.elapsed(), &allocator);
 
-    let total_duration = total_start.elapsed();
     println!(
-        "Total time for {} with {} iterations: {}",
-        format!("BUMP ALLOCATOR").purple(),
-        iterations.to_string().bold().green(),
-        format!("{:?}", total_duration).bold().green()
+        "\n{} requests of {} phases with {} u64s each",
+        num_requests.to_string().bold(),
+        phases,
+        allocations_per_phase
     );
+}
 
-    println!("\n");
-    // Standard Allocator
-    println!("==== MEASURE STANADARD ALLOCATOR ====");
-    let mut std_allocations: Vec<*mut u64> = Vec::with_capacity(num_allocations * iterations);
+fn fill_phase(phase: &Frame, allocations_per_phase: usize) -> u64 {
+    (0..allocations_per_phase)
+        .map(|i| *phase.alloc(i as u64))
+        .sum()
+}
 
-    let total_start = Instant::now();
+fn print_scope_result(name: &str, duration: Duration, allocator: &BumpAllocator) {
+    println!(
+        "{}: {}, high-water mark {} bytes",
+        name.blue(),
+        format!("{:?}", duration).bold().green(),
+        allocator.high_water_mark().to_string().yellow()
+    );
+}
+
+/// Intern `count` strings, of which `distinct` are different, with a [`BumpInterner`]
+/// and with a `HashMap<String, u32>` that owns its keys on the system heap.
+pub fn measure_interner(count: usize, distinct: usize, backends: &[Backend]) {
+    println!("\n /////////////// Measure string interning ////////////// \n");
+    let words: Vec<String> = (0..distinct.max(1)).map(|i| format!("word{i}")).collect();
+    let inputs = || (0..count).map(|i| words[i % words.len()].as_str());
+    let bytes: usize = words.iter().map(String::len).sum();
+
+    if backends.contains(&Backend::Bump) {
+        let allocator = BumpAllocator::new(bytes);
+        let mut interner = BumpInterner::new(&allocator);
+        let start = Instant::now();
+        let checksum: u64 = inputs()
+            .map(|word| interner.intern(word).as_u32() as u64)
+            .sum();
+        print_interner_result(
+            "Bump Interner",
+            start.elapsed(),
+            interner.len(),
+            allocator.used(),
+            checksum,
+        );
+    }
 
-    for iter in 0..iterations {
+    if backends.contains(&Backend::System) {
+        let mut symbols: HashMap<String, u32> = HashMap::new();
         let start = Instant::now();
+        let checksum: u64 = inputs()
+            .map(|word| match symbols.get(word) {
+                Some(&symbol) => symbol as u64,
+                None => {
+                    let symbol = symbols.len() as u32;
+                    symbols.insert(word.to_owned(), symbol);
+                    symbol as u64
+                }
+            })
+            .sum();
+        print_interner_result(
+            "System HashMap<String, u32>",
+            start.elapsed(),
+            symbols.len(),
+            symbols.keys().map(String::capacity).sum(),
+            checksum,
+        );
+    }
+}
+
+fn print_interner_result(
+    name: &str,
+    duration: Duration,
+    distinct: usize,
+    string_bytes: usize,
+    checksum: u64,
+) {
+    println!(
+        "{}: Interned {} distinct strings in {}, {} bytes of strings (checksum {})",
+        name.blue(),
+        distinct.to_string().green(),
+        format!("{:?}", duration).bold().green(),
+        string_bytes.to_string().yellow(),
+        checksum
+    );
+}
 
+/// Compare a chunked bump allocator, which starts small and grows, with a fixed buffer sized up front.
+pub fn measure_chunked_allocator(
+    num_allocations: usize,
+    first_chunk_size: usize,
+    backends: &[Backend],
+) {
+    println!("\n /////////////// Measure chunked allocator ////////////// \n");
+    let size = mem::size_of::<u64>();
+
+    if backends.contains(&Backend::Bump) {
+        // Fixed Bump Allocator
+        let allocator = BumpAllocator::new(num_allocations * size * 2);
+        let start = Instant::now();
+        let mut allocations: Vec<&mut u64> = Vec::with_capacity(num_allocations);
         for i in 0..num_allocations {
-            let boxed = Box::new(i as u64);
-            std_allocations.push(Box::into_raw(boxed));
+            allocations.push(allocator.alloc(i as u64));
         }
+        let duration = start.elapsed();
+        println!(
+            "{}: Allocated {} u64s in {}",
+            "Fixed Bump Allocator".blue(),
+            num_allocations.to_string().green(),
+            format!("{:?}", duration).bold().green()
+        );
+        drop(allocations);
+    }
 
+    if backends.contains(&Backend::Chunked) {
+        // Chunked Bump Allocator
+        let allocator = ChunkedBumpAllocator::new(first_chunk_size);
+        let start = Instant::now();
+        let mut allocations: Vec<&mut u64> = Vec::with_capacity(num_allocations);
+        for i in 0..num_allocations {
+            allocations.push(allocator.alloc(i as u64));
+        }
         let duration = start.elapsed();
         println!(
-            "Iteration {} - Allocated {} u64s in {:?}",
-            iter + 1,
-            num_allocations,
-            duration
+            "{}: Allocated {} u64s in {} using {} chunks ({} bytes)",
+            "Chunked Bump Allocator".blue(),
+            num_allocations.to_string().green(),
+            format!("{:?}", duration).bold().green(),
+            allocator.chunk_count(),
+            allocator.allocated_bytes()
         );
+        drop(allocations);
     }
 
-    let total_duration = total_start.elapsed();
-    println!(
-        "Total time for {} with {} iterations: {}",
-        format!("STANDARD ALLOCATOR").purple(),
-        iterations.to_string().bold().green(),
-        format!("{:?}", total_duration).bold().green()
-    );
+    if backends.contains(&Backend::System) {
+        // Standard Allocator
+        let start = Instant::now();
+        let std_allocations: Vec<Box<u64>> =
+            (0..num_allocations).map(|i| Box::new(i as u64)).collect();
+        let duration = start.elapsed();
+        println!(
+            "{}: Allocated {} u64s in {}",
+            "Standard Allocator".blue(),
+            num_allocations.to_string().green(),
+            format!("{:?}", duration).bold().green()
+        );
+        drop(std_allocations);
+    }
+}
 
-    // Cleanup
-    for ptr in std_allocations {
-        unsafe {
-            let _ = Box::from_raw(ptr);
+/// Compare `Vec` and `Box` backed by the bump allocator with the standard allocator.
+#[cfg(feature = "nightly")]
+pub fn measure_collections(num_elements: usize, backends: &[Backend]) {
+    println!("\n /////////////// Measure standard collections ////////////// \n");
+
+    println!("{}", "Measure Vec<u64>...".purple().italic());
+    if backends.contains(&Backend::Bump) {
+        // Growing by doubling leaves every smaller copy behind in the bump buffer
+        let allocator = BumpAllocator::new(num_elements * mem::size_of::<u64>() * 4);
+        let start = Instant::now();
+        let mut bump_vec = Vec::new_in(&allocator);
+        for i in 0..num_elements {
+            bump_vec.push(i as u64);
         }
+        let duration = start.elapsed();
+        println!(
+            "{}: pushed {} u64s in {}",
+            "Bump Allocator".blue(),
+            num_elements.to_string().green(),
+            format!("{:?}", duration).bold().green()
+        );
+        drop(bump_vec);
     }
-}
 
-// /// Test multithreaded allocations using ThreadSafeBumpAllocator and standard allocator.
-// fn test_multithreaded_allocations() {
-//     println!("Testing multithreaded allocations...");
-//     let num_threads = 8;
-//     let num_allocations_per_thread = 100_000;
-
-//     // Thread-safe Bump Allocator
-//     let size = mem::size_of::<u64>();
-//     let align = mem::align_of::<u64>();
-//     let buffer_size = num_threads * num_allocations_per_thread * size * 2;
-//     let allocator = Arc::new(ThreadSafeBumpAllocator::new(buffer_size));
-
-//     let start = Instant::now();
-
-//     let mut handles = Vec::new();
-
-//     for _ in 0..num_threads {
-//         let alloc_ref = Arc::clone(&allocator);
-//         let handle = thread::spawn(move || {
-//             let mut local_allocations: Vec<*mut u64> = Vec::with_capacity(num_allocations_per_thread);
-//             for i in 0..num_allocations_per_thread {
-//                 let ptr = alloc_ref.allocate(size, align) as *mut u64;
-//                 unsafe {
-//                     ptr::write(ptr, i as u64);
-//                 }
-//                 local_allocations.push(ptr);
-//             }
-//             local_allocations
-//         });
-//         handles.push(handle);
-//     }
-
-//     let mut all_allocations = Vec::with_capacity(num_threads * num_allocations_per_thread);
-
-//     for handle in handles {
-//         let mut thread_allocations = handle.join().unwrap();
-//         all_allocations.append(&mut thread_allocations);
-//     }
-
-//     let duration = start.elapsed();
-//     println!(
-//         "ThreadSafe Bump Allocator: Allocated {} u64s across {} threads in {:?}",
-//         num_threads * num_allocations,
-//         num_threads,
-//         duration
-//     );
-
-//     allocator.reset();
-
-//     // Standard Allocator
-//     let start = Instant::now();
-
-//     let mut handles = Vec::new();
-
-//     for _ in 0..num_threads {
-//         let handle = thread::spawn(move || {
-//             let mut local_allocations: Vec<*mut u64> = Vec::with_capacity(num_allocations);
-//             for i in 0..num_allocations {
-//                 let boxed = Box::new(i as u64);
-//                 local_allocations.push(Box::into_raw(boxed));
-//             }
-//             local_allocations
-//         });
-//         handles.push(handle);
-//     }
-
-//     let mut all_std_allocations = Vec::with_capacity(num_threads * num_allocations);
-
-//     for handle in handles {
-//         let mut thread_allocations = handle.join().unwrap();
-//         all_std_allocations.append(&mut thread_allocations);
-//     }
-
-//     let duration = start.elapsed();
-//     println!(
-//         "Standard Allocator: Allocated {} u64s across {} threads in {:?}",
-//         num_threads * num_allocations,
-//         num_threads,
-//         duration
-//     );
-
-//     // Cleanup
-//     for ptr in all_std_allocations {
-//         unsafe {
-//             Box::from_raw(ptr);
-//         }
-//     }
-
-//     println!("----------------------------------------");
-// }
-
-// /// Test the bump allocator and standard allocator under stress by allocating until memory is exhausted.
-// fn test_stress_allocations() {
-//     println!("Testing stress allocations...");
-//     // Adjust these numbers based on your system's memory
-//     let allocation_size = mem::size_of::<u64>();
-//     let align = mem::align_of::<u64>();
-//     let buffer_size = 10 * 1024 * 1024; // 10 MB
-
-//     // Bump Allocator
-//     let allocator = BumpAllocator::new(buffer_size);
-//     let mut allocations: Vec<*mut u64> = Vec::new();
-//     let start = Instant::now();
-
-//     println!("Starting stress test for Bump Allocator...");
-
-//     loop {
-//         if allocations.len() % 100_000 == 0 && allocations.len() != 0 {
-//             println!(
-//                 "Bump Allocator: Allocated {} u64s so far...",
-//                 allocations.len()
-//             );
-//         }
-
-//         let ptr = match std::panic::catch_unwind(|| allocator.allocate(allocation_size, align)) {
-//             Ok(p) => p as *mut u64,
-//             Err(_) => {
-//                 println!(
-//                     "Bump Allocator: Failed after allocating {} u64s.",
-//                     allocations.len()
-//                 );
-//                 break;
-//             }
-//         };
-
-//         unsafe {
-//             ptr::write(ptr, allocations.len() as u64);
-//         }
-//         allocations.push(ptr);
-//     }
-
-//     let duration = start.elapsed();
-//     println!(
-//         "Bump Allocator: Allocated {} u64s in {:?}",
-//         allocations.len(),
-//         duration
-//     );
-
-//     // No reset performed here as we are stressing the allocator
-
-//     // Standard Allocator
-//     let mut std_allocations: Vec<*mut u64> = Vec::new();
-//     let start = Instant::now();
-
-//     println!("Starting stress test for Standard Allocator...");
-
-//     loop {
-//         if std_allocations.len() % 100_000 == 0 && std_allocations.len() != 0 {
-//             println!(
-//                 "Standard Allocator: Allocated {} u64s so far...",
-//                 std_allocations.len()
-//             );
-//         }
-
-//         // Attempt to allocate; handle out-of-memory gracefully
-//         let boxed = Box::try_new(0u64);
-//         match boxed {
-//             Ok(b) => std_allocations.push(Box::into_raw(b)),
-//             Err(_) => {
-//                 println!(
-//                     "Standard Allocator: Failed after allocating {} u64s.",
-//                     std_allocations.len()
-//                 );
-//                 break;
-//             }
-//         }
-//     }
-
-//     let duration = start.elapsed();
-//     println!(
-//         "Standard Allocator: Allocated {} u64s in {:?}",
-//         std_allocations.len(),
-//         duration
-//     );
-
-//     // Cleanup
-//     for ptr in std_allocations {
-//         unsafe {
-//             Box::from_raw(ptr);
-//         }
-//     }
-
-//     println!("----------------------------------------");
-// }
+    if backends.contains(&Backend::System) {
+        let start = Instant::now();
+        let mut std_vec = Vec::new();
+        for i in 0..num_elements {
+            std_vec.push(i as u64);
+        }
+        let duration = start.elapsed();
+        println!(
+            "{}: pushed {} u64s in {}",
+            "Standard Allocator".blue(),
+            num_elements.to_string().green(),
+            format!("{:?}", duration).bold().green()
+        );
+        drop(std_vec);
+    }
+
+    println!("\n");
+    println!("{}", "Measure Box<u64>...".purple().italic());
+    if backends.contains(&Backend::Bump) {
+        let allocator = BumpAllocator::new(num_elements * mem::size_of::<u64>());
+        let start = Instant::now();
+        let bump_boxes: Vec<Box<u64, &BumpAllocator>> = (0..num_elements)
+            .map(|i| Box::new_in(i as u64, &allocator))
+            .collect();
+        let duration = start.elapsed();
+        println!(
+            "{}: boxed {} u64s in {}",
+            "Bump Allocator".blue(),
+            num_elements.to_string().green(),
+            format!("{:?}", duration).bold().green()
+        );
+        drop(bump_boxes);
+    }
+
+    if backends.contains(&Backend::System) {
+        let start = Instant::now();
+        let std_boxes: Vec<Box<u64>> = (0..num_elements).map(|i| Box::new(i as u64)).collect();
+        let duration = start.elapsed();
+        println!(
+            "{}: boxed {} u64s in {}",
+            "Standard Allocator".blue(),
+            num_elements.to_string().green(),
+            format!("{:?}", duration).bold().green()
+        );
+        drop(std_boxes);
+    }
+}
@@ -0,0 +1,134 @@
+//! Anonymous memory mappings backing [`BumpAllocator::with_mmap`] on Linux.
+//!
+//! [`BumpAllocator::with_mmap`]: crate::allocator::BumpAllocator::with_mmap
+
+use std::io;
+use std::ptr::{self, NonNull};
+
+/// Size of a transparent huge page on x86-64 and most aarch64 kernels.
+const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// How [`BumpAllocator::with_mmap`](crate::allocator::BumpAllocator::with_mmap) maps its
+/// buffer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MmapOptions {
+    /// Align the mapping to 2 MiB and ask for transparent huge pages with
+    /// `MADV_HUGEPAGE`. The kernel may still use small pages.
+    pub huge_pages: bool,
+}
+
+/// Private anonymous mapping. Its pages are committed only when first touched.
+pub(crate) struct MmapRegion {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// SAFETY: the region is owned memory like a heap buffer.
+unsafe impl Send for MmapRegion {}
+
+impl MmapRegion {
+    /// Map at least `len` bytes, rounded up to whole pages.
+    pub(crate) fn new(len: usize, options: MmapOptions) -> io::Result<Self> {
+        let page = page_size();
+        let len = len
+            .max(1)
+            .checked_next_multiple_of(page)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+
+        if !options.huge_pages {
+            return Ok(MmapRegion {
+                ptr: map(len)?,
+                len,
+            });
+        }
+
+        // Map a huge page more than needed and unmap both ends, so the region starts
+        // on a huge page boundary
+        let padded = len
+            .checked_add(HUGE_PAGE_SIZE)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+        let mapping = map(padded)?.as_ptr();
+        let head = mapping.align_offset(HUGE_PAGE_SIZE);
+        let tail = padded - head - len;
+        unsafe {
+            if head > 0 {
+                libc::munmap(mapping.cast(), head);
+            }
+            if tail > 0 {
+                libc::munmap(mapping.add(head + len).cast(), tail);
+            }
+        }
+
+        let region = MmapRegion {
+            ptr: unsafe { NonNull::new_unchecked(mapping.add(head)) },
+            len,
+        };
+        // Only advice: kernels without transparent huge pages reject it
+        unsafe { libc::madvise(region.ptr.as_ptr().cast(), len, libc::MADV_HUGEPAGE) };
+        Ok(region)
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    /// Give the pages holding bytes `from..to` of the region back to the kernel. A
+    /// page only partly in the range is kept if it lies before `from`, and discarded if
+    /// it lies after `to`, since the region owns the rest of that page.
+    pub(crate) fn discard(&self, from: usize, to: usize) {
+        let to = to.next_multiple_of(page_size()).min(self.len);
+        if from < to {
+            unsafe { discard_pages(self.ptr.as_ptr().add(from), to - from) };
+        }
+    }
+}
+
+/// Give the whole pages in the `len` bytes at `start` back to the kernel with
+/// `MADV_DONTNEED`. They read as zero and are committed again when next touched.
+/// Pages only partly in the range are kept.
+///
+/// # Safety
+///
+/// The range must be private anonymous memory owned by the caller, such as a mapping
+/// or a heap block, with nothing in it that is still needed.
+pub(crate) unsafe fn discard_pages(start: *mut u8, len: usize) {
+    let page = page_size();
+    let first = start.align_offset(page);
+    if first >= len {
+        return;
+    }
+    let whole = (len - first) / page * page;
+    if whole > 0 {
+        libc::madvise(start.add(first).cast(), whole, libc::MADV_DONTNEED);
+    }
+}
+
+impl Drop for MmapRegion {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.as_ptr().cast(), self.len);
+        }
+    }
+}
+
+fn map(len: usize) -> io::Result<NonNull<u8>> {
+    let ptr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            // NORESERVE: no swap is set aside, pages are only committed on first touch
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { NonNull::new_unchecked(ptr.cast()) })
+}
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
@@ -1,57 +1,214 @@
-use std::alloc::{alloc, dealloc, Layout};
+use bump_core::{AllocError, Bump, BumpPosition};
+use std::mem;
+use std::ptr::NonNull;
 use std::sync::Mutex;
-use std::{mem, usize};
-use crate::align_up;
 
-pub struct ThreadSafeBumpAllocator {
-    buffer: *mut u8,
-    capacity: usize,
-    offset: Mutex<usize>,
+#[cfg(loom)]
+use loom::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+#[cfg(not(loom))]
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::allocator::{alloc_failed, write_value, Buffer};
+
+/// Set in `SharedBumpAllocator::handles` while a reset waits for the handles to drop.
+const RESETTING: usize = 1 << (usize::BITS - 1);
+
+/// Bump allocator that can be shared between threads.
+///
+/// `P` decides how concurrent allocations are ordered: [`ThreadSafeBumpAllocator`]
+/// takes a lock for every allocation, [`AtomicBumpAllocator`] uses a compare-and-swap
+/// loop instead.
+///
+/// Threads allocate through an [`AllocHandle`]. [`reset`](Self::reset) waits until
+/// every handle has been dropped and keeps new ones from being taken meanwhile, so
+/// it can be called while other threads are still allocating.
+pub struct SharedBumpAllocator<P> {
+    buffer: Buffer,
+    bump: Bump<P>,
+    /// Live handles, with `RESETTING` set while a reset is in progress.
+    handles: AtomicUsize,
+    /// Largest `used()` seen at a reset.
+    high_water_mark: AtomicUsize,
 }
 
-impl ThreadSafeBumpAllocator {
+pub type ThreadSafeBumpAllocator = SharedBumpAllocator<Mutex<usize>>;
+
+/// Lock-free variant of [`ThreadSafeBumpAllocator`].
+pub type AtomicBumpAllocator = SharedBumpAllocator<AtomicUsize>;
+
+// SAFETY: `buffer` is owned by the allocator and only reached through blocks handed
+// out by `bump`. Every update of the position goes through `P`, which for `P: Sync`
+// applies it as a single step, so concurrent calls get disjoint blocks. A block is
+// handed out again only after `reset`, which waits for every `AllocHandle` to drop,
+// so memory borrowed from a handle is never shared between two owners.
+unsafe impl<P: Send> Send for SharedBumpAllocator<P> {}
+unsafe impl<P: Sync> Sync for SharedBumpAllocator<P> {}
+
+impl<P: BumpPosition + Default> SharedBumpAllocator<P> {
     pub fn new(capacity: usize) -> Self {
-        let layout =
-            Layout::from_size_align(capacity, mem::align_of::<usize>()).expect("Invalid layout");
-        let buffer = unsafe { alloc(layout) };
-        if buffer.is_null() {
-            panic!("failed to allocate buffer for PumpAllocator");
-        }
+        Self::with_alignment(capacity, mem::align_of::<usize>())
+    }
+
+    /// Like [`new`](Self::new), with the buffer aligned as for
+    /// [`BumpAllocator::with_alignment`](crate::allocator::BumpAllocator::with_alignment).
+    pub fn with_alignment(capacity: usize, align: usize) -> Self {
+        let buffer = Buffer::new(capacity, align).unwrap_or_else(|error| {
+            panic!("failed to allocate buffer for SharedBumpAllocator: {error}")
+        });
 
-        ThreadSafeBumpAllocator {
+        SharedBumpAllocator {
+            bump: Bump::new(buffer.as_ptr() as usize, capacity, P::default()),
             buffer,
-            capacity,
-            offset: Mutex::new(0),
+            handles: AtomicUsize::new(0),
+            high_water_mark: AtomicUsize::new(0),
         }
     }
 
-    pub fn allocate(&self, size: usize, align: usize) -> *mut u8 {
-        let mut current_offset = self.offset.lock().unwrap();
-        let start = align_up(*current_offset, align);
-        let end = start
-            .checked_add(size)
-            .expect("overflow in allocation size");
-
-        if end > self.capacity {
-            panic!("BumpAllocator out of memory")
+    /// Take a handle to allocate from, waiting for a reset in progress to finish.
+    ///
+    /// References returned by the handle stay valid until it is dropped; `reset`
+    /// does not reuse their memory before then.
+    pub fn handle(&self) -> AllocHandle<'_, P> {
+        let mut state = self.handles.load(Ordering::Relaxed);
+        loop {
+            if state & RESETTING != 0 {
+                thread::yield_now();
+                state = self.handles.load(Ordering::Relaxed);
+                continue;
+            }
+            // Acquire pairs with the release at the end of `reset`
+            match self.handles.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return AllocHandle { allocator: self },
+                Err(actual) => state = actual,
+            }
         }
+    }
+
+    /// Reserve `size` bytes aligned to `align` and return a raw pointer to them.
+    ///
+    /// # Safety
+    ///
+    /// The pointer is not tied to an [`AllocHandle`], so `reset` does not wait for
+    /// it. The caller must not use it after the next `reset` starts or after the
+    /// allocator is dropped.
+    pub unsafe fn allocate(&self, size: usize, align: usize) -> *mut u8 {
+        self.try_allocate(size, align)
+            .unwrap_or_else(|error| alloc_failed(error))
+            .as_ptr()
+    }
 
-        *current_offset = end;
-        unsafe { self.buffer.add(start) }
+    /// Like [`allocate`](Self::allocate), but reports which limit was hit instead of
+    /// panicking.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`allocate`](Self::allocate).
+    pub unsafe fn try_allocate(
+        &self,
+        size: usize,
+        align: usize,
+    ) -> Result<NonNull<u8>, AllocError> {
+        let offset = self.bump.alloc(size, align)?;
+        Ok(NonNull::new_unchecked(self.buffer.as_ptr().add(offset)))
     }
 
+    /// Free every allocation at once.
+    ///
+    /// Waits until all handles have been dropped, and blocks new ones until the reset
+    /// is done. Calling it while this thread holds a handle never returns.
     pub fn reset(&self) {
-        let mut current_offset = self.offset.lock().unwrap();
-        *current_offset = 0;
+        // Claim the reset, waiting out one already in progress
+        let mut state = self.handles.load(Ordering::Relaxed);
+        loop {
+            if state & RESETTING != 0 {
+                thread::yield_now();
+                state = self.handles.load(Ordering::Relaxed);
+                continue;
+            }
+            match self.handles.compare_exchange_weak(
+                state,
+                state | RESETTING,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => state = actual,
+            }
+        }
+
+        // No handle can be taken now, so wait for the live ones to drop. Acquire makes
+        // their writes to the buffer happen before the memory is handed out again.
+        while self.handles.load(Ordering::Acquire) != RESETTING {
+            thread::yield_now();
+        }
+
+        self.high_water_mark
+            .fetch_max(self.bump.used(), Ordering::Relaxed);
+        self.bump.reset();
+        self.handles.store(0, Ordering::Release);
+    }
+
+    /// Bytes consumed since the last reset, alignment padding included. Other threads
+    /// may be allocating, so the value can be stale as soon as it is returned.
+    pub fn used(&self) -> usize {
+        self.bump.used()
+    }
+
+    /// Bytes still free in the buffer, with the same caveat as [`used`](Self::used).
+    pub fn remaining(&self) -> usize {
+        self.bump.capacity() - self.bump.used()
+    }
+
+    /// Most bytes ever consumed between two resets.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+            .load(Ordering::Relaxed)
+            .max(self.bump.used())
     }
 }
 
-impl Drop for ThreadSafeBumpAllocator {
-    fn drop(&mut self) {
-        let layout = Layout::from_size_align(self.capacity, mem::align_of::<usize>())
-            .expect("Invalid layout");
+/// Lets one thread allocate from a [`SharedBumpAllocator`]. Memory handed out by the
+/// handle is not reused by `reset` until the handle is dropped.
+pub struct AllocHandle<'a, P> {
+    allocator: &'a SharedBumpAllocator<P>,
+}
+
+impl<P: BumpPosition + Default> AllocHandle<'_, P> {
+    /// Move `value` into the allocator. The reference borrows the handle. The value's
+    /// destructor is never run.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        self.try_alloc(value)
+            .unwrap_or_else(|error| alloc_failed(error))
+    }
+
+    /// Like [`alloc`](Self::alloc), but reports which limit was hit instead of panicking.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T>(&self, value: T) -> Result<&mut T, AllocError> {
+        // The handle keeps `reset` from reusing the block while the reference lives
         unsafe {
-            dealloc(self.buffer, layout);
+            let block = self
+                .allocator
+                .try_allocate(mem::size_of::<T>(), mem::align_of::<T>())?;
+            Ok(write_value(block, value))
         }
     }
-}
\ No newline at end of file
+}
+
+impl<P> Drop for AllocHandle<'_, P> {
+    fn drop(&mut self) {
+        // Release pairs with the acquire in `reset`
+        self.allocator.handles.fetch_sub(1, Ordering::Release);
+    }
+}
@@ -0,0 +1,186 @@
+//! Multithreaded comparison of the shared bump allocators, thread-local arenas and the
+//! system allocator.
+//!
+//! Each allocator is run twice per thread count: once untimed per allocation to
+//! measure throughput, and once timing every allocation to measure tail latency. The
+//! latency figures include the cost of reading the clock.
+
+use colored::Colorize;
+use std::mem;
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::backend::Backend;
+use crate::multi_thread_allocator::{AtomicBumpAllocator, ThreadSafeBumpAllocator};
+use crate::thread_local_arena::ArenaPool;
+
+/// Compare the mutex and lock-free bump allocators, thread-local arenas and the
+/// standard allocator, allocating `allocations_per_thread` u64s on each of
+/// `thread_counts` threads. Only the Mutex, Atomic, Arenas and System `backends` are
+/// run.
+pub fn measure_multithreaded_allocators(
+    thread_counts: &[usize],
+    allocations_per_thread: usize,
+    backends: &[Backend],
+) {
+    println!("\n /////////////// Measure multithreaded allocators ////////////// \n");
+    let size = mem::size_of::<u64>();
+
+    for &num_threads in thread_counts {
+        println!(
+            "{}",
+            format!("Measure {} threads...", num_threads)
+                .purple()
+                .italic()
+        );
+        let buffer_size = num_threads * allocations_per_thread * size;
+
+        if backends.contains(&Backend::Mutex) {
+            let allocator = ThreadSafeBumpAllocator::new(buffer_size);
+            measure(
+                "Mutex Bump Allocator",
+                num_threads,
+                allocations_per_thread,
+                || allocator.handle(),
+                |handle, i| {
+                    handle.alloc(i);
+                },
+                || allocator.reset(),
+            );
+        }
+
+        if backends.contains(&Backend::Atomic) {
+            let allocator = AtomicBumpAllocator::new(buffer_size);
+            measure(
+                "Atomic Bump Allocator",
+                num_threads,
+                allocations_per_thread,
+                || allocator.handle(),
+                |handle, i| {
+                    handle.alloc(i);
+                },
+                || allocator.reset(),
+            );
+        }
+
+        if backends.contains(&Backend::Arenas) {
+            // One block per thread
+            let pool = ArenaPool::new(allocations_per_thread * size);
+            measure(
+                "Thread-Local Arenas",
+                num_threads,
+                allocations_per_thread,
+                || pool.arena(),
+                |arena, i| {
+                    arena.alloc(i);
+                },
+                || pool.request_reset(),
+            );
+        }
+
+        if backends.contains(&Backend::System) {
+            // Boxes are freed after timing, like the bump buffers
+            measure(
+                "Standard Allocator",
+                num_threads,
+                allocations_per_thread,
+                || Vec::with_capacity(allocations_per_thread),
+                |boxes, i| boxes.push(Box::new(i)),
+                || {},
+            );
+        }
+
+        println!();
+    }
+}
+
+/// Run `alloc` `allocations_per_thread` times on each of `num_threads` threads, with
+/// per-thread state from `per_thread`, and print throughput and latency percentiles.
+/// `reset` is called after each pass, once every thread's state has been dropped.
+fn measure<S: Send>(
+    name: &str,
+    num_threads: usize,
+    allocations_per_thread: usize,
+    per_thread: impl Fn() -> S + Sync,
+    alloc: impl Fn(&mut S, u64) + Sync,
+    reset: impl Fn(),
+) {
+    let (duration, states) = run_threads(num_threads, || {
+        let mut state = per_thread();
+        for i in 0..allocations_per_thread {
+            alloc(&mut state, i as u64);
+        }
+        state
+    });
+    drop(states);
+    reset();
+
+    let (_, results) = run_threads(num_threads, || {
+        let mut state = per_thread();
+        let mut latencies = Vec::with_capacity(allocations_per_thread);
+        for i in 0..allocations_per_thread {
+            let start = Instant::now();
+            alloc(&mut state, i as u64);
+            latencies.push(start.elapsed());
+        }
+        (state, latencies)
+    });
+    let mut latencies: Vec<Duration> = results
+        .into_iter()
+        .flat_map(|(_, latencies)| latencies)
+        .collect();
+    reset();
+    latencies.sort_unstable();
+
+    let num_allocations = num_threads * allocations_per_thread;
+    let throughput = num_allocations as f64 / duration.as_secs_f64() / 1e6;
+    println!(
+        "{}: {} M allocs/s, p50 {}, p99 {}, p99.9 {}, max {}",
+        name.blue(),
+        format!("{:.1}", throughput).bold().green(),
+        format!("{:?}", percentile(&latencies, 0.5)).green(),
+        format!("{:?}", percentile(&latencies, 0.99)).green(),
+        format!("{:?}", percentile(&latencies, 0.999)).yellow(),
+        format!("{:?}", percentile(&latencies, 1.0)).red()
+    );
+}
+
+/// Value below which a `fraction` of the sorted `latencies` fall.
+fn percentile(latencies: &[Duration], fraction: f64) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let index = ((latencies.len() - 1) as f64 * fraction).round() as usize;
+    latencies[index]
+}
+
+/// Run `work` on `num_threads` threads at once and time until all of them finish. The
+/// threads wait at a barrier until all are running, and the clock runs from there to
+/// the end of the last `work`, so spawning and joining them is not timed.
+fn run_threads<R: Send>(num_threads: usize, work: impl Fn() -> R + Sync) -> (Duration, Vec<R>) {
+    let start_line = Barrier::new(num_threads + 1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..num_threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    start_line.wait();
+                    let result = work();
+                    (Instant::now(), result)
+                })
+            })
+            .collect();
+        start_line.wait();
+        let start = Instant::now();
+        let mut end = start;
+        let results = handles
+            .into_iter()
+            .map(|handle| {
+                let (finished, result) = handle.join().unwrap();
+                end = end.max(finished);
+                result
+            })
+            .collect();
+        (end - start, results)
+    })
+}
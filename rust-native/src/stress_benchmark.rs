@@ -0,0 +1,179 @@
+//! Fill the bump allocator and a limited system allocator until they run out.
+//!
+//! Exhaustion is detected through the `try_` APIs, so nothing panics or unwinds. For
+//! each allocator the benchmark reports how many allocations fit, the share of the
+//! consumed memory lost to alignment padding, and the time until the first failure.
+
+use colored::Colorize;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ptr::NonNull;
+use std::time::{Duration, Instant};
+
+use crate::allocator::{AllocError, BumpAllocator};
+use crate::backend::Backend;
+
+/// `(size, align)` requests of mixed sizes, repeated until the allocator runs out.
+const MIXED: [(usize, usize); 8] = [
+    (8, 8),
+    (1, 1),
+    (24, 8),
+    (3, 1),
+    (64, 16),
+    (2, 2),
+    (128, 64),
+    (12, 4),
+];
+
+/// System allocator that fails once `limit` bytes are in use, so it can be run out of
+/// memory like a bump buffer.
+///
+/// Each allocation is charged its size padded to its alignment. The allocator's own
+/// headers and size classes are not visible here, so they are not counted.
+pub struct LimitedSystemAllocator {
+    limit: usize,
+    used: usize,
+    blocks: Vec<(NonNull<u8>, Layout)>,
+}
+
+impl LimitedSystemAllocator {
+    pub fn new(limit: usize) -> Self {
+        LimitedSystemAllocator {
+            limit,
+            used: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn try_allocate(&mut self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        let charged = layout.pad_to_align().size();
+        if charged > self.limit - self.used {
+            return Err(AllocError::OutOfMemory);
+        }
+        if layout.size() == 0 {
+            // Zero-sized blocks need no memory, only an aligned address
+            return Ok(unsafe { NonNull::new_unchecked(layout.align() as *mut u8) });
+        }
+
+        let ptr = NonNull::new(unsafe { System.alloc(layout) }).ok_or(AllocError::OutOfMemory)?;
+        self.used += charged;
+        self.blocks.push((ptr, layout));
+        Ok(ptr)
+    }
+
+    /// Bytes charged against the limit so far.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+}
+
+impl Drop for LimitedSystemAllocator {
+    fn drop(&mut self) {
+        for (ptr, layout) in self.blocks.drain(..) {
+            unsafe { System.dealloc(ptr.as_ptr(), layout) }
+        }
+    }
+}
+
+/// Outcome of filling one allocator.
+struct StressResult {
+    allocations: usize,
+    /// Bytes asked for.
+    requested: usize,
+    /// Bytes taken from the allocator, alignment padding included.
+    consumed: usize,
+    duration: Duration,
+    error: AllocError,
+}
+
+/// Fill a `budget`-byte bump allocator and a limited system allocator, first with
+/// `u64`s and then with requests of mixed sizes and alignments. Only the Bump and
+/// System `backends` are run.
+pub fn measure_stress_allocations(budget: usize, backends: &[Backend]) {
+    println!("\n /////////////// Measure stress allocations ////////////// \n");
+
+    for (workload, pattern) in [("u64", &[(8, 8)][..]), ("mixed sizes", &MIXED[..])] {
+        println!("{}", format!("Measure {}...", workload).purple().italic());
+        if backends.contains(&Backend::Bump) {
+            print_result("Bump Allocator", &fill_bump(budget, pattern));
+        }
+        if backends.contains(&Backend::System) {
+            print_result("Limited System Allocator", &fill_system(budget, pattern));
+        }
+        println!();
+    }
+}
+
+fn fill_bump(budget: usize, pattern: &[(usize, usize)]) -> StressResult {
+    let allocator = BumpAllocator::new(budget);
+    let (mut allocations, mut requested) = (0, 0);
+    // Padding before the first block is not counted
+    let mut first = None;
+    let mut end = 0;
+    let start = Instant::now();
+
+    loop {
+        let (size, align) = pattern[allocations % pattern.len()];
+        // The blocks are never used, only counted
+        match unsafe { allocator.try_allocate(size, align) } {
+            Ok(ptr) => {
+                let address = ptr.as_ptr() as usize;
+                first.get_or_insert(address);
+                end = address + size;
+                allocations += 1;
+                requested += size;
+            }
+            Err(error) => {
+                return StressResult {
+                    allocations,
+                    requested,
+                    consumed: first.map_or(0, |first| end - first),
+                    duration: start.elapsed(),
+                    error,
+                };
+            }
+        }
+    }
+}
+
+fn fill_system(budget: usize, pattern: &[(usize, usize)]) -> StressResult {
+    let mut allocator = LimitedSystemAllocator::new(budget);
+    let (mut allocations, mut requested) = (0, 0);
+    let start = Instant::now();
+
+    loop {
+        let (size, align) = pattern[allocations % pattern.len()];
+        let layout = Layout::from_size_align(size, align).expect("Invalid layout");
+        match allocator.try_allocate(layout) {
+            Ok(_) => {
+                allocations += 1;
+                requested += size;
+            }
+            Err(error) => {
+                return StressResult {
+                    allocations,
+                    requested,
+                    consumed: allocator.used(),
+                    duration: start.elapsed(),
+                    error,
+                };
+            }
+        }
+    }
+}
+
+fn print_result(name: &str, result: &StressResult) {
+    let overhead = if result.consumed == 0 {
+        0.0
+    } else {
+        (result.consumed - result.requested) as f64 / result.consumed as f64 * 100.0
+    };
+    println!(
+        "{}: {} allocations ({} bytes) in {} before {:?}, {} padding",
+        name.blue(),
+        result.allocations.to_string().green(),
+        result.requested,
+        format!("{:?}", result.duration).bold().green(),
+        result.error,
+        format!("{:.1}%", overhead).yellow()
+    );
+}
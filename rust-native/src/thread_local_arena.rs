@@ -0,0 +1,215 @@
+use bump_core::check_align;
+use std::cell::RefCell;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::{cmp, mem};
+
+use crate::allocator::{alloc_failed, write_value, AllocError};
+use crate::chunked_allocator::Chunk;
+
+/// Shared supply of fixed-size blocks for per-thread [`ThreadArena`]s.
+///
+/// Each thread bumps through blocks of its own, so threads only meet here when one
+/// of them needs a new block. Blocks come back through a global epoch reset: after
+/// [`request_reset`](Self::request_reset) every arena hands its blocks in at its next
+/// [`quiescent_point`](ThreadArena::quiescent_point), and they are reused only once
+/// every arena has done so. Raw pointers one thread passed to another therefore stay
+/// valid until all threads have moved on.
+pub struct ArenaPool {
+    block_size: usize,
+    epoch: AtomicU64,
+    state: Mutex<PoolState>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    /// Blocks ready to hand out.
+    free: Vec<Chunk>,
+    /// Blocks handed in, waiting for every arena to pass a quiescent point.
+    retired: Vec<Chunk>,
+    /// Live arenas.
+    arenas: usize,
+    /// Live arenas that have not passed a quiescent point since the last reset request.
+    behind: usize,
+    blocks_created: usize,
+}
+
+impl PoolState {
+    fn catch_up(&mut self) {
+        self.behind -= 1;
+        if self.behind == 0 {
+            self.release_retired();
+        }
+    }
+
+    fn release_retired(&mut self) {
+        for block in self.retired.drain(..) {
+            block.reset();
+            self.free.push(block);
+        }
+    }
+}
+
+impl ArenaPool {
+    pub fn new(block_size: usize) -> Arc<Self> {
+        Arc::new(ArenaPool {
+            block_size: cmp::max(block_size, mem::size_of::<usize>()),
+            epoch: AtomicU64::new(0),
+            state: Mutex::new(PoolState::default()),
+        })
+    }
+
+    /// Register a new arena, normally one per thread.
+    pub fn arena(self: &Arc<Self>) -> ThreadArena {
+        let mut state = self.lock();
+        state.arenas += 1;
+        ThreadArena {
+            pool: Arc::clone(self),
+            blocks: RefCell::new(Vec::new()),
+            // A new arena holds nothing from earlier epochs
+            epoch: self.epoch.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Start a new epoch. Every block in use is returned to the pool once each live
+    /// arena has reached a quiescent point.
+    pub fn request_reset(&self) {
+        let mut state = self.lock();
+        self.epoch.fetch_add(1, Ordering::Release);
+        state.behind = state.arenas;
+        if state.behind == 0 {
+            state.release_retired();
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::Acquire)
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Blocks ready to be handed to an arena without asking the system allocator.
+    pub fn free_blocks(&self) -> usize {
+        self.lock().free.len()
+    }
+
+    /// Blocks obtained from the system allocator so far.
+    pub fn blocks_created(&self) -> usize {
+        self.lock().blocks_created
+    }
+
+    fn take_block(&self) -> Result<Chunk, AllocError> {
+        let mut state = self.lock();
+        if let Some(block) = state.free.pop() {
+            return Ok(block);
+        }
+        let block = Chunk::new(self.block_size)?;
+        state.blocks_created += 1;
+        Ok(block)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Bump arena owned by one thread, fed with blocks from an [`ArenaPool`].
+pub struct ThreadArena {
+    pool: Arc<ArenaPool>,
+    blocks: RefCell<Vec<Chunk>>,
+    /// Last epoch this arena has caught up with.
+    epoch: u64,
+}
+
+impl ThreadArena {
+    /// Move `value` into the arena. The reference borrows the arena, so it cannot be
+    /// used after the next `quiescent_point`. The value's destructor is never run.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        self.try_alloc(value)
+            .unwrap_or_else(|error| alloc_failed(error))
+    }
+
+    /// Like [`alloc`](Self::alloc), but reports which limit was hit instead of panicking.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T>(&self, value: T) -> Result<&mut T, AllocError> {
+        unsafe {
+            let block = self.try_allocate(mem::size_of::<T>(), mem::align_of::<T>())?;
+            Ok(write_value(block, value))
+        }
+    }
+
+    /// Reserve `size` bytes aligned to `align`, taking a new block from the pool if the
+    /// current one is full. Requests larger than a block fail with `OutOfMemory`.
+    ///
+    /// # Safety
+    ///
+    /// The pointer does not borrow the arena. It may be shared with other threads, but
+    /// must not be used once every arena has passed a quiescent point after the next
+    /// [`ArenaPool::request_reset`].
+    pub unsafe fn try_allocate(
+        &self,
+        size: usize,
+        align: usize,
+    ) -> Result<NonNull<u8>, AllocError> {
+        // Checked before a block is taken for a request that could never be served
+        check_align(align)?;
+        let mut blocks = self.blocks.borrow_mut();
+        if let Some(block) = blocks.last() {
+            match block.try_allocate(size, align) {
+                Err(AllocError::OutOfMemory) => {}
+                result => return result,
+            }
+        }
+
+        // Blocks are only aligned to `usize`, so leave room to align the request
+        let required = size
+            .checked_add(align.saturating_sub(mem::align_of::<usize>()))
+            .ok_or(AllocError::Overflow)?;
+        if required > self.pool.block_size {
+            return Err(AllocError::OutOfMemory);
+        }
+
+        let block = self.pool.take_block()?;
+        let result = block.try_allocate(size, align);
+        blocks.push(block);
+        result
+    }
+
+    /// Tell the pool this thread holds no references into its blocks. Taking `&mut self`
+    /// makes the compiler check that for references returned by `alloc`.
+    ///
+    /// If a reset was requested since the last quiescent point, the arena hands all of
+    /// its blocks back and continues with fresh ones.
+    pub fn quiescent_point(&mut self) {
+        // Fast path: no reset requested, nothing to do
+        if self.epoch == self.pool.epoch() {
+            return;
+        }
+
+        let mut state = self.pool.lock();
+        state.retired.append(self.blocks.get_mut());
+        self.epoch = self.pool.epoch.load(Ordering::Relaxed);
+        state.catch_up();
+    }
+
+    /// Blocks currently held by this arena.
+    pub fn block_count(&self) -> usize {
+        self.blocks.borrow().len()
+    }
+}
+
+impl Drop for ThreadArena {
+    fn drop(&mut self) {
+        // Dropping is a quiescent point; the blocks wait for the next completed epoch
+        let mut state = self.pool.lock();
+        state.retired.append(self.blocks.get_mut());
+        state.arenas -= 1;
+        if self.epoch != self.pool.epoch.load(Ordering::Relaxed) {
+            state.catch_up();
+        }
+    }
+}
@@ -0,0 +1,304 @@
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+
+use optimize_bump_allocation::allocator::{AllocError, BumpAllocator, ResetPolicy, POISON};
+
+#[test]
+fn alloc_returns_values_borrowed_from_the_allocator() {
+    let bump = BumpAllocator::new(1024);
+
+    let value = bump.alloc(7u64);
+    let values = bump.alloc_slice_copy(&[1u32, 2, 3]);
+    let text = bump.alloc_str("bump");
+    *value += 1;
+    values[0] = 10;
+    text.make_ascii_uppercase();
+
+    assert_eq!(*value, 8);
+    assert_eq!(values, &[10, 2, 3]);
+    assert_eq!(text, "BUMP");
+    assert_eq!(value as *mut u64 as usize % std::mem::align_of::<u64>(), 0);
+}
+
+#[test]
+fn reset_reuses_memory_once_borrows_end() {
+    let mut bump = BumpAllocator::new(16);
+
+    let first = bump.alloc(1u64) as *mut u64 as usize;
+    bump.alloc(2u64);
+    bump.reset();
+    let again = bump.alloc(3u64) as *mut u64 as usize;
+
+    assert_eq!(first, again);
+}
+
+#[test]
+fn raw_allocate_remains_available() {
+    let bump = BumpAllocator::new(64);
+    let ptr = unsafe { bump.allocate(16, 16) };
+    assert_eq!(ptr as usize % 16, 0);
+    unsafe { ptr.write_bytes(0xAB, 16) };
+}
+
+#[test]
+fn try_alloc_reports_which_limit_was_hit() {
+    let bump = BumpAllocator::new(16);
+
+    assert!(bump.try_alloc(1u64).is_ok());
+    assert!(bump.try_alloc(2u64).is_ok());
+    assert_eq!(bump.try_alloc(3u8).unwrap_err(), AllocError::OutOfMemory);
+
+    let overflow = unsafe { bump.try_allocate(usize::MAX, 1) };
+    assert_eq!(overflow.unwrap_err(), AllocError::Overflow);
+}
+
+#[test]
+fn usage_counts_padding_and_high_water_mark_survives_reset() {
+    let mut bump = BumpAllocator::new(64);
+
+    bump.alloc(1u8);
+    bump.alloc(2u64);
+    assert_eq!(bump.used() + bump.remaining(), 64);
+    assert!(bump.used() >= 9);
+    let peak = bump.used();
+
+    bump.reset();
+    bump.alloc(3u8);
+    assert_eq!(bump.used(), 1);
+    assert_eq!(bump.remaining(), 63);
+    assert_eq!(bump.high_water_mark(), peak);
+}
+
+#[test]
+fn alloc_with_drop_runs_destructors_in_reverse_on_reset_and_drop() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Noisy(u32, Rc<RefCell<Vec<u32>>>);
+    impl Drop for Noisy {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    let dropped = Rc::new(RefCell::new(Vec::new()));
+    let mut bump = BumpAllocator::new(1024);
+
+    bump.alloc_with_drop(Noisy(1, dropped.clone()));
+    bump.alloc_with_drop(7u64);
+    bump.alloc_with_drop(Noisy(2, dropped.clone()));
+    let owned = bump.alloc_with_drop(vec![String::from("heap")]);
+    owned.push(String::from("data"));
+    assert_eq!(bump.pending_drops(), 3);
+
+    bump.reset();
+    assert_eq!(*dropped.borrow(), [2, 1]);
+    assert_eq!(bump.pending_drops(), 0);
+
+    bump.alloc_with_drop(Noisy(3, dropped.clone()));
+    drop(bump);
+    assert_eq!(*dropped.borrow(), [2, 1, 3]);
+    assert_eq!(Rc::strong_count(&dropped), 1);
+}
+
+#[test]
+fn blocks_are_aligned_by_address_up_to_page_size() {
+    let bump = BumpAllocator::new(64 * 1024);
+
+    for shift in 0..=12 {
+        let align = 1 << shift;
+        // A 1-byte block first, so the next offset is never aligned by accident
+        unsafe { bump.allocate(1, 1) };
+        let ptr = unsafe { bump.allocate(align, align) };
+        assert_eq!(ptr as usize % align, 0, "align {}", align);
+    }
+}
+
+#[test]
+fn base_alignment_avoids_padding_for_simd_values() {
+    #[derive(Clone, Copy)]
+    #[repr(align(32))]
+    struct F32x8([f32; 8]);
+
+    #[derive(Clone, Copy)]
+    #[repr(align(64))]
+    struct F32x16([f32; 16]);
+
+    let bump = BumpAllocator::with_alignment(1024, 64);
+    let lanes = bump.alloc_slice_copy(&[F32x8([1.0; 8]); 3]);
+    assert_eq!(lanes.as_ptr() as usize % 32, 0);
+    assert_eq!(bump.used(), 3 * 32);
+
+    let wide = bump.alloc(F32x16([2.0; 16]));
+    assert_eq!(wide as *mut F32x16 as usize % 64, 0);
+    assert_eq!(bump.used(), 2 * 64 + 64);
+    assert_eq!(lanes[2].0[7] + wide.0[15], 3.0);
+}
+
+#[test]
+fn page_aligned_buffer_holds_whole_pages() {
+    let page = 4096;
+    let bump = BumpAllocator::with_alignment(4 * page, page);
+
+    for _ in 0..4 {
+        let ptr = bump.try_alloc([0u8; 4096]).unwrap();
+        assert_eq!(ptr.as_ptr() as usize % page, 0);
+    }
+    assert_eq!(bump.remaining(), 0);
+}
+
+#[test]
+fn alignment_must_be_a_power_of_two() {
+    let bump = BumpAllocator::new(64);
+
+    for align in [0, 3, 12, usize::MAX] {
+        let result = unsafe { bump.try_allocate(8, align) };
+        assert_eq!(result.unwrap_err(), AllocError::InvalidAlignment);
+    }
+    assert_eq!(bump.used(), 0);
+}
+
+#[test]
+#[should_panic(expected = "alignment is not a power of two")]
+fn allocate_panics_on_invalid_alignment() {
+    let bump = BumpAllocator::new(64);
+    unsafe { bump.allocate(8, 6) };
+}
+
+#[test]
+fn empty_buffer_serves_only_zero_sized_values() {
+    let mut bump = BumpAllocator::with_alignment(0, 64);
+
+    assert_eq!(bump.try_alloc(1u8).unwrap_err(), AllocError::OutOfMemory);
+    let unit = bump.alloc(());
+    assert_eq!(unit as *mut () as usize % 64, 0);
+    bump.reset();
+    assert_eq!(bump.remaining(), 0);
+}
+
+/// Fill `bump` with `0xEE` bytes, reset it, and return the freed bytes as they now read.
+fn reset_and_read(bump: &mut BumpAllocator, len: usize) -> Vec<u8> {
+    let ptr = unsafe { bump.allocate(len, 1) };
+    unsafe { ptr.write_bytes(0xEE, len) };
+    bump.reset();
+    // The buffer is still owned by `bump`, only its contents are stale
+    unsafe { std::slice::from_raw_parts(ptr, len) }.to_vec()
+}
+
+#[test]
+fn reset_policies_decide_what_stale_memory_reads_as() {
+    let mut bump = BumpAllocator::new(256);
+    assert_eq!(bump.reset_policy(), ResetPolicy::Plain);
+    assert!(reset_and_read(&mut bump, 256)
+        .iter()
+        .all(|&byte| byte == 0xEE));
+
+    bump.set_reset_policy(ResetPolicy::Zero);
+    assert!(reset_and_read(&mut bump, 256).iter().all(|&byte| byte == 0));
+
+    bump.set_reset_policy(ResetPolicy::Poison);
+    let expected = if cfg!(debug_assertions) { POISON } else { 0xEE };
+    assert!(reset_and_read(&mut bump, 256)
+        .iter()
+        .all(|&byte| byte == expected));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn release_policy_returns_whole_pages_above_the_threshold() {
+    let page = 4096;
+    let mut bump = BumpAllocator::with_alignment(8 * page, page);
+    bump.set_reset_policy(ResetPolicy::Release {
+        threshold: 2 * page + 100,
+    });
+
+    let bytes = reset_and_read(&mut bump, 8 * page);
+    // Pages up to and including the one holding the threshold are kept
+    assert!(bytes[..3 * page].iter().all(|&byte| byte == 0xEE));
+    assert!(bytes[3 * page..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn nested_scopes_free_only_their_own_allocations() {
+    let mut bump = BumpAllocator::new(1024);
+    bump.alloc(0u64);
+
+    let parsed = bump.scope(|request| {
+        let header = request.alloc_str("request");
+        let before_phase = request.used();
+
+        let parsed = request.scope(|phase| {
+            let temporaries = phase.alloc_slice_copy(&[1u64, 2, 3, 4]);
+            phase.scope(|inner| {
+                inner.alloc(5u64);
+            });
+            temporaries.iter().sum::<u64>()
+        });
+        assert_eq!(request.used(), before_phase);
+
+        // Earlier allocations in the request are still usable
+        header.make_ascii_uppercase();
+        assert_eq!(header, "REQUEST");
+        parsed
+    });
+
+    assert_eq!(parsed, 10);
+    assert_eq!(bump.used(), 8);
+    assert!(bump.high_water_mark() >= 8 + 7 + 32 + 8);
+}
+
+#[test]
+fn scope_rewinds_and_runs_destructors_on_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    let counter = Rc::new(());
+    let mut bump = BumpAllocator::new(256);
+    bump.alloc_with_drop(counter.clone());
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        bump.scope(|frame| {
+            frame.alloc_with_drop(counter.clone());
+            frame.alloc([0u8; 100]);
+            panic!("phase failed");
+        })
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(bump.used(), std::mem::size_of::<Rc<()>>());
+    assert_eq!(bump.pending_drops(), 1);
+    assert_eq!(Rc::strong_count(&counter), 2);
+}
+
+#[cfg(feature = "nightly")]
+#[test]
+fn std_collections_allocate_from_the_buffer() {
+    let capacity = 1024;
+    let bump = BumpAllocator::new(capacity);
+    // An empty block sits at the start of the buffer and uses nothing
+    let start = unsafe { bump.allocate(0, 1) } as usize;
+    let in_buffer = |ptr: *const u64| (start..start + capacity).contains(&(ptr as usize));
+
+    let boxed = Box::new_in(7u64, &bump);
+    assert!(in_buffer(&*boxed));
+    assert_eq!(*boxed, 7);
+    assert_eq!(bump.used(), 8);
+
+    let mut values = Vec::with_capacity_in(4, &bump);
+    values.extend(0..4u64);
+    assert!(in_buffer(values.as_ptr()));
+    assert_eq!(bump.used(), 8 + 4 * 8);
+
+    // Growing copies the values into a new block, the old one stays used until reset
+    values.reserve_exact(12);
+    values.extend(4..16);
+    assert!(in_buffer(values.as_ptr()));
+    assert_eq!(values, (0..16).collect::<Vec<_>>());
+    assert_eq!(bump.used(), 8 + 4 * 8 + 16 * 8);
+
+    // So does shrinking
+    values.truncate(3);
+    values.shrink_to_fit();
+    assert!(in_buffer(values.as_ptr()));
+    assert_eq!(values, [0, 1, 2]);
+    assert_eq!(bump.used(), 8 + 4 * 8 + 16 * 8 + 3 * 8);
+}
@@ -0,0 +1,67 @@
+use optimize_bump_allocation::allocator::AllocError;
+use optimize_bump_allocation::chunked_allocator::ChunkedBumpAllocator;
+
+#[test]
+fn grows_geometrically_instead_of_running_out() {
+    let bump = ChunkedBumpAllocator::new(64);
+
+    let values: Vec<&mut u64> = (0..100).map(|i| bump.alloc(i)).collect();
+    assert!(values.iter().enumerate().all(|(i, value)| **value == i as u64));
+
+    // 64 + 128 + 256 + 512 bytes hold the 800 bytes of values
+    assert_eq!(bump.chunk_count(), 4);
+    assert_eq!(bump.allocated_bytes(), 64 + 128 + 256 + 512);
+}
+
+#[test]
+fn oversized_and_over_aligned_requests_get_a_chunk_that_fits() {
+    let bump = ChunkedBumpAllocator::new(64);
+
+    let block = unsafe { bump.try_allocate(1000, 256) }.unwrap();
+    assert_eq!(block.as_ptr() as usize % 256, 0);
+    let large = bump.alloc([7u8; 4096]);
+    assert!(large.iter().all(|&byte| byte == 7));
+}
+
+#[test]
+fn memory_limit_caps_total_chunk_bytes() {
+    let bump = ChunkedBumpAllocator::with_memory_limit(64, 200);
+
+    let mut count = 0;
+    while bump.try_alloc(0u64).is_ok() {
+        count += 1;
+    }
+    assert_eq!(count, 200 / 8);
+    assert_eq!(bump.allocated_bytes(), 200);
+    assert_eq!(bump.try_alloc(0u8).unwrap_err(), AllocError::OutOfMemory);
+    assert_eq!(
+        unsafe { bump.try_allocate(usize::MAX, 16) }.unwrap_err(),
+        AllocError::Overflow
+    );
+}
+
+#[test]
+fn reset_keeps_only_the_largest_chunk() {
+    let mut bump = ChunkedBumpAllocator::new(64);
+    for i in 0..100u64 {
+        bump.alloc(i);
+    }
+    assert_eq!(bump.chunk_count(), 4);
+
+    bump.reset();
+    assert_eq!(bump.chunk_count(), 1);
+    assert_eq!(bump.allocated_bytes(), 512);
+
+    for i in 0..64u64 {
+        bump.alloc(i);
+    }
+    assert_eq!(bump.chunk_count(), 1);
+}
+
+#[test]
+fn invalid_alignment_adds_no_chunk() {
+    let bump = ChunkedBumpAllocator::new(64);
+    let result = unsafe { bump.try_allocate(8, 5) };
+    assert_eq!(result.unwrap_err(), AllocError::InvalidAlignment);
+    assert_eq!(bump.chunk_count(), 0);
+}
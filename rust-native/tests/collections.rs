@@ -0,0 +1,109 @@
+use optimize_bump_allocation::allocator::BumpAllocator;
+use optimize_bump_allocation::collections::{BumpBox, BumpString, BumpVec};
+use std::cell::Cell;
+use std::fmt::Write;
+
+/// Counts how many times it has been dropped.
+struct DropCounter<'a>(&'a Cell<usize>);
+
+impl Drop for DropCounter<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn bump_box_derefs_and_drops_its_value() {
+    let bump = BumpAllocator::new(1024);
+    let drops = Cell::new(0);
+
+    let mut boxed = BumpBox::new_in(41u64, &bump);
+    *boxed += 1;
+    assert_eq!(*boxed, 42);
+    assert_eq!(boxed.into_inner(), 42);
+
+    drop(BumpBox::new_in(DropCounter(&drops), &bump));
+    assert_eq!(drops.get(), 1);
+
+    let counter = BumpBox::new_in(DropCounter(&drops), &bump).into_inner();
+    assert_eq!(drops.get(), 1);
+    drop(counter);
+    assert_eq!(drops.get(), 2);
+}
+
+#[test]
+fn bump_vec_grows_and_keeps_its_elements() {
+    let bump = BumpAllocator::new(64 * 1024);
+    let mut values = BumpVec::new_in(&bump);
+
+    for i in 0..1_000u64 {
+        values.push(i);
+    }
+    assert_eq!(values.len(), 1_000);
+    assert!(values.iter().copied().eq(0..1_000));
+    assert_eq!(values.pop(), Some(999));
+
+    values.truncate(10);
+    values.extend(100..103);
+    values.extend_from_slice(&[7, 8]);
+    assert_eq!(&values[..], &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 100, 101, 102, 7, 8]);
+}
+
+#[test]
+fn bump_vec_runs_destructors_on_truncate_and_drop() {
+    let bump = BumpAllocator::new(4 * 1024);
+    let drops = Cell::new(0);
+
+    let mut values = BumpVec::with_capacity_in(2, &bump);
+    for _ in 0..10 {
+        values.push(DropCounter(&drops));
+    }
+    // Growing moves the elements instead of dropping them
+    assert_eq!(drops.get(), 0);
+
+    values.truncate(6);
+    assert_eq!(drops.get(), 4);
+    drop(values.pop());
+    assert_eq!(drops.get(), 5);
+    drop(values);
+    assert_eq!(drops.get(), 10);
+}
+
+#[test]
+fn bump_vec_of_zero_sized_values_uses_no_memory() {
+    let bump = BumpAllocator::new(8);
+    let mut values = BumpVec::new_in(&bump);
+    for _ in 0..1_000 {
+        values.push(());
+    }
+    assert_eq!(values.len(), 1_000);
+}
+
+#[test]
+fn bump_string_builds_utf8_text() {
+    let bump = BumpAllocator::new(1024);
+    let mut text = BumpString::from_str_in("bump", &bump);
+    text.push(' ');
+    text.push_str("allocated");
+    text.push('✓');
+    write!(text, " {}", 42).unwrap();
+
+    assert_eq!(text.as_str(), "bump allocated✓ 42");
+    assert_eq!(text.to_string(), "bump allocated✓ 42");
+    assert!(text.capacity() >= text.len());
+
+    text.clear();
+    assert!(text.is_empty());
+}
+
+#[test]
+fn reset_is_possible_once_collections_are_gone() {
+    let mut bump = BumpAllocator::new(64);
+    {
+        let mut values = BumpVec::with_capacity_in(8, &bump);
+        values.extend_from_slice(&[1u64; 8]);
+    }
+    bump.reset();
+    let values = BumpVec::<u64>::with_capacity_in(8, &bump);
+    assert_eq!(values.capacity(), 8);
+}
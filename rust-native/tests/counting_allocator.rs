@@ -0,0 +1,30 @@
+use optimize_bump_allocation::counting_allocator::{self, CountingAlloc};
+use std::alloc::System;
+
+#[global_allocator]
+static GLOBAL: CountingAlloc<System> = CountingAlloc::new(System);
+
+// One test only, so no other test thread allocates while the counters are read
+#[test]
+fn counts_calls_live_and_peak_bytes() {
+    counting_allocator::register(&GLOBAL);
+    let counters = counting_allocator::registered().unwrap();
+    counters.reset_peak();
+    let before = counters.stats();
+
+    let boxed = Box::new([0u8; 100]);
+    let mut values: Vec<u32> = Vec::with_capacity(4);
+    values.reserve_exact(8);
+    let grown = counters.stats().since(before);
+    assert_eq!(grown.allocations, 2);
+    assert_eq!(grown.reallocs, 1);
+    assert_eq!(grown.live_bytes, before.live_bytes + 100 + 32);
+    assert_eq!(grown.peak_bytes, grown.live_bytes);
+
+    drop(boxed);
+    drop(values);
+    let freed = counters.stats().since(before);
+    assert_eq!(freed.frees, 2);
+    assert_eq!(freed.live_bytes, before.live_bytes);
+    assert_eq!(freed.peak_bytes, grown.peak_bytes);
+}
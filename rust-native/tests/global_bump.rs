@@ -0,0 +1,64 @@
+use optimize_bump_allocation::global_bump::{GlobalBumpAllocator, REGION_ALIGN};
+use std::alloc::{GlobalAlloc, Layout};
+
+#[global_allocator]
+static GLOBAL: GlobalBumpAllocator = GlobalBumpAllocator::new(1024 * 1024);
+
+#[test]
+fn runs_a_program_as_the_global_allocator() {
+    let boxed = Box::new(7u64);
+    let mut values: Vec<u64> = (0..1000).collect();
+    values.push(1000);
+    let text = format!("{} values", values.len());
+
+    assert!(GLOBAL.contains(&*boxed as *const u64 as *const u8));
+    assert!(GLOBAL.contains(values.as_ptr() as *const u8));
+    assert_eq!(text, "1001 values");
+    assert!(GLOBAL.used() > 0);
+}
+
+#[test]
+fn falls_back_to_the_system_allocator_when_full() {
+    let bump = GlobalBumpAllocator::new(64);
+    let small = Layout::from_size_align(48, 8).unwrap();
+
+    unsafe {
+        let first = bump.alloc(small);
+        assert!(bump.contains(first));
+        assert_eq!(first as usize % REGION_ALIGN, 0);
+
+        let second = bump.alloc(small);
+        assert!(!second.is_null());
+        assert!(!bump.contains(second));
+        assert_eq!(bump.fallbacks(), 1);
+
+        // Alignments above the region's never fit in it
+        let page = Layout::from_size_align(8, 2 * REGION_ALIGN).unwrap();
+        let aligned = bump.alloc(page);
+        assert!(!bump.contains(aligned));
+        assert_eq!(aligned as usize % (2 * REGION_ALIGN), 0);
+        assert_eq!(bump.fallbacks(), 2);
+
+        // Routed by address: only the fallback blocks go back to the system allocator
+        bump.dealloc(first, small);
+        bump.dealloc(second, small);
+        bump.dealloc(aligned, page);
+    }
+}
+
+#[test]
+fn realloc_copies_out_of_the_region() {
+    let bump = GlobalBumpAllocator::new(64);
+    let layout = Layout::from_size_align(16, 8).unwrap();
+
+    unsafe {
+        let ptr = bump.alloc(layout);
+        ptr.write_bytes(0xAB, 16);
+        assert_eq!(bump.realloc(ptr, layout, 8), ptr);
+
+        let grown = bump.realloc(ptr, layout, 128);
+        assert!(!bump.contains(grown));
+        assert_eq!(*grown.add(15), 0xAB);
+        bump.dealloc(grown, Layout::from_size_align(128, 8).unwrap());
+    }
+}
@@ -0,0 +1,54 @@
+use bump_core::AllocError;
+use optimize_bump_allocation::allocator::BumpAllocator;
+use optimize_bump_allocation::interner::{BumpInterner, InternError};
+
+#[test]
+fn equal_strings_share_a_symbol_and_a_copy() {
+    let bump = BumpAllocator::new(1024);
+    let mut interner = BumpInterner::new(&bump);
+
+    let apple = interner.intern("apple");
+    let pear = interner.intern("pear");
+    assert_ne!(apple, pear);
+    assert_eq!(interner.intern(&String::from("apple")), apple);
+    assert_eq!(interner.len(), 2);
+    assert_eq!(bump.used(), "applepear".len());
+
+    assert_eq!(interner.resolve(apple), "apple");
+    assert_eq!(interner.get("pear"), Some(pear));
+    assert_eq!(interner.get("plum"), None);
+
+    let first = interner.intern_str("pear");
+    let second = interner.intern_str("pear");
+    assert_eq!(first.as_ptr(), second.as_ptr());
+    assert_eq!(
+        interner.iter().collect::<Vec<_>>(),
+        [(apple, "apple"), (pear, "pear")]
+    );
+}
+
+#[test]
+fn strings_outlive_the_interner() {
+    let bump = BumpAllocator::new(64);
+    let name = {
+        let mut interner = BumpInterner::new(&bump);
+        interner.intern_str("name")
+    };
+    assert_eq!(name, "name");
+}
+
+#[test]
+fn full_allocator_interns_nothing() {
+    let bump = BumpAllocator::new(8);
+    let mut interner = BumpInterner::new(&bump);
+
+    let short = interner.try_intern("short").unwrap();
+    assert_eq!(
+        interner.try_intern("too long"),
+        Err(InternError::Alloc(AllocError::OutOfMemory))
+    );
+    assert_eq!(interner.len(), 1);
+    assert_eq!(interner.get("too long"), None);
+    // Known strings need no memory
+    assert_eq!(interner.try_intern("short"), Ok(short));
+}
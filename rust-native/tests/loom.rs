@@ -0,0 +1,42 @@
+//! Model checks of the handle/reset protocol in `SharedBumpAllocator`, run with
+//! `RUSTFLAGS="--cfg loom" cargo test --release --test loom`.
+#![cfg(loom)]
+
+use loom::sync::Arc;
+use loom::thread;
+use optimize_bump_allocation::multi_thread_allocator::AtomicBumpAllocator;
+
+#[test]
+fn reset_never_reuses_memory_held_by_a_handle() {
+    loom::model(|| {
+        let allocator = Arc::new(AtomicBumpAllocator::new(16));
+
+        let other = Arc::clone(&allocator);
+        let worker = thread::spawn(move || {
+            let handle = other.handle();
+            let value = handle.alloc(1u64);
+            thread::yield_now();
+            assert_eq!(*value, 1);
+        });
+
+        allocator.reset();
+        let handle = allocator.handle();
+        *handle.alloc(0u64) = 2;
+        drop(handle);
+
+        worker.join().unwrap();
+    });
+}
+
+#[test]
+fn concurrent_handles_get_disjoint_blocks() {
+    loom::model(|| {
+        let allocator = Arc::new(AtomicBumpAllocator::new(16));
+
+        let other = Arc::clone(&allocator);
+        let worker = thread::spawn(move || other.handle().alloc(0u64) as *mut u64 as usize);
+        let address = allocator.handle().alloc(0u64) as *mut u64 as usize;
+
+        assert_ne!(address, worker.join().unwrap());
+    });
+}
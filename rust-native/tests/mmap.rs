@@ -0,0 +1,45 @@
+#![cfg(target_os = "linux")]
+
+use optimize_bump_allocation::allocator::BumpAllocator;
+use optimize_bump_allocation::mmap::MmapOptions;
+
+#[test]
+fn mapped_buffer_allocates_like_a_heap_buffer() {
+    let bump = BumpAllocator::with_mmap(64 * 1024, MmapOptions::default());
+
+    let first = bump.alloc(7u64);
+    let values = bump.alloc_slice_copy(&[1u32, 2, 3]);
+    assert_eq!(*first, 7);
+    assert_eq!(values, &[1, 2, 3]);
+    assert_eq!(first as *mut u64 as usize % 4096, 0);
+    assert_eq!(bump.remaining(), 64 * 1024 - 20);
+}
+
+#[test]
+fn reset_gives_pages_back_and_they_read_as_zero() {
+    let mut bump = BumpAllocator::with_mmap(3 * 4096, MmapOptions::default());
+
+    let page = bump.alloc([0xABu8; 5000]) as *mut [u8; 5000] as usize;
+    bump.reset();
+    let again = bump.alloc([0u8; 0]) as *mut [u8; 0] as usize;
+    assert_eq!(page, again);
+
+    // The memory is only read through the allocator's own buffer
+    let bytes = unsafe { std::slice::from_raw_parts(page as *const u8, 5000) };
+    assert!(bytes.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn huge_pages_align_the_buffer_to_two_mebibytes() {
+    let bump = BumpAllocator::with_mmap(4 * 1024 * 1024, MmapOptions { huge_pages: true });
+    let first = bump.alloc(1u8) as *mut u8 as usize;
+    assert_eq!(first % (2 * 1024 * 1024), 0);
+}
+
+#[test]
+fn large_buffers_only_reserve_address_space() {
+    // 64 GiB would not fit in memory if the pages were committed up front
+    let bump = BumpAllocator::with_mmap(64 << 30, MmapOptions::default());
+    *bump.alloc(1u64) += 1;
+    assert_eq!(bump.remaining(), (64 << 30) - 8);
+}
@@ -0,0 +1,123 @@
+use optimize_bump_allocation::allocator::AllocError;
+use optimize_bump_allocation::multi_thread_allocator::{
+    AtomicBumpAllocator, ThreadSafeBumpAllocator,
+};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn try_allocate_reports_which_limit_was_hit() {
+    let allocator = ThreadSafeBumpAllocator::new(16);
+
+    unsafe {
+        let first = allocator.try_allocate(8, 8).unwrap();
+        let second = allocator.try_allocate(8, 8).unwrap();
+        assert_eq!(second.as_ptr() as usize - first.as_ptr() as usize, 8);
+        assert_eq!(allocator.try_allocate(1, 1), Err(AllocError::OutOfMemory));
+        assert_eq!(
+            allocator.try_allocate(usize::MAX, 1),
+            Err(AllocError::Overflow)
+        );
+
+        allocator.reset();
+        assert_eq!(allocator.try_allocate(8, 8), Ok(first));
+    }
+}
+
+#[test]
+fn reset_waits_for_live_handles() {
+    let allocator = AtomicBumpAllocator::new(8);
+
+    let address = thread::scope(|scope| {
+        let handle = allocator.handle();
+        let value = handle.alloc(1u64);
+        let reset = scope.spawn(|| allocator.reset());
+        thread::sleep(Duration::from_millis(50));
+        assert!(!reset.is_finished(), "reset must wait for the handle");
+
+        *value += 1;
+        assert_eq!(*value, 2);
+        let address = value as *mut u64 as usize;
+        drop(handle);
+        reset.join().unwrap();
+        address
+    });
+
+    let handle = allocator.handle();
+    assert_eq!(handle.alloc(3u64) as *mut u64 as usize, address);
+}
+
+#[test]
+fn concurrent_allocations_are_disjoint() {
+    fn check(allocate: impl Fn() -> usize + Sync) {
+        let mut addresses: Vec<usize> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| (0..1_000).map(|_| allocate()).collect::<Vec<_>>()))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        addresses.sort_unstable();
+        assert!(addresses.windows(2).all(|pair| pair[1] - pair[0] >= 8));
+        assert_eq!(addresses.len(), 8_000);
+    }
+
+    let mutex = ThreadSafeBumpAllocator::new(8 * 1_000 * 8);
+    check(|| mutex.handle().alloc(0u64) as *mut u64 as usize);
+    let atomic = AtomicBumpAllocator::new(8 * 1_000 * 8);
+    check(|| atomic.handle().alloc(0u64) as *mut u64 as usize);
+    assert_eq!(atomic.handle().try_alloc(0u8), Err(AllocError::OutOfMemory));
+}
+
+#[test]
+fn usage_is_reported_across_resets() {
+    let allocator = AtomicBumpAllocator::new(64);
+
+    {
+        let handle = allocator.handle();
+        handle.alloc(1u64);
+        handle.alloc(2u64);
+    }
+    assert_eq!(allocator.used(), 16);
+    assert_eq!(allocator.remaining(), 48);
+
+    allocator.reset();
+    allocator.handle().alloc(3u32);
+    assert_eq!(allocator.used(), 4);
+    assert_eq!(allocator.high_water_mark(), 16);
+}
+
+#[test]
+fn base_alignment_applies_to_shared_allocators() {
+    let allocator = ThreadSafeBumpAllocator::with_alignment(2 * 4096, 4096);
+
+    unsafe {
+        let first = allocator.try_allocate(4096, 4096).unwrap();
+        let second = allocator.try_allocate(1, 4096).unwrap();
+        assert_eq!(first.as_ptr() as usize % 4096, 0);
+        assert_eq!(second.as_ptr() as usize - first.as_ptr() as usize, 4096);
+    }
+    assert_eq!(allocator.remaining(), 4095);
+}
+
+#[test]
+fn alignment_must_be_a_power_of_two() {
+    let allocator = ThreadSafeBumpAllocator::new(64);
+    assert_eq!(
+        unsafe { allocator.try_allocate(8, 24) },
+        Err(AllocError::InvalidAlignment)
+    );
+}
+
+#[test]
+fn empty_buffer_serves_only_zero_sized_blocks() {
+    let allocator = AtomicBumpAllocator::with_alignment(0, 64);
+    unsafe {
+        assert_eq!(allocator.try_allocate(1, 1), Err(AllocError::OutOfMemory));
+        let block = allocator.try_allocate(0, 64).unwrap();
+        assert_eq!(block.as_ptr() as usize % 64, 0);
+    }
+    allocator.reset();
+}
@@ -0,0 +1,31 @@
+use optimize_bump_allocation::allocator::AllocError;
+use optimize_bump_allocation::stress_benchmark::LimitedSystemAllocator;
+use std::alloc::Layout;
+
+#[test]
+fn limited_system_allocator_stops_at_its_limit() {
+    let mut allocator = LimitedSystemAllocator::new(32);
+    let layout = Layout::new::<u64>();
+
+    for _ in 0..4 {
+        let ptr = allocator.try_allocate(layout).unwrap();
+        unsafe { ptr.cast::<u64>().as_ptr().write(1) };
+    }
+    assert_eq!(allocator.used(), 32);
+    assert_eq!(allocator.try_allocate(layout), Err(AllocError::OutOfMemory));
+}
+
+#[test]
+fn limited_system_allocator_charges_alignment_padding() {
+    let mut allocator = LimitedSystemAllocator::new(64);
+
+    let ptr = allocator
+        .try_allocate(Layout::from_size_align(3, 16).unwrap())
+        .unwrap();
+    assert_eq!(ptr.as_ptr() as usize % 16, 0);
+    assert_eq!(allocator.used(), 16);
+    assert!(allocator
+        .try_allocate(Layout::from_size_align(0, 8).unwrap())
+        .is_ok());
+    assert_eq!(allocator.used(), 16);
+}
@@ -0,0 +1,90 @@
+use optimize_bump_allocation::allocator::AllocError;
+use optimize_bump_allocation::thread_local_arena::ArenaPool;
+
+#[test]
+fn arenas_take_blocks_from_the_pool() {
+    let pool = ArenaPool::new(64);
+    let arena = pool.arena();
+
+    let values: Vec<&mut u64> = (0..20).map(|i| arena.alloc(i)).collect();
+    assert!(values
+        .iter()
+        .enumerate()
+        .all(|(i, value)| **value == i as u64));
+    assert_eq!(arena.block_count(), 3);
+    assert_eq!(pool.blocks_created(), 3);
+    assert_eq!(
+        arena.try_alloc([0u8; 65]).unwrap_err(),
+        AllocError::OutOfMemory
+    );
+}
+
+#[test]
+fn blocks_return_only_after_every_arena_is_quiescent() {
+    let pool = ArenaPool::new(64);
+    let mut first = pool.arena();
+    let mut second = pool.arena();
+    first.alloc(1u64);
+    second.alloc(2u64);
+
+    // Without a reset request, quiescent points keep the blocks
+    first.quiescent_point();
+    assert_eq!(first.block_count(), 1);
+
+    pool.request_reset();
+    first.quiescent_point();
+    assert_eq!(first.block_count(), 0);
+    assert_eq!(pool.free_blocks(), 0, "second arena has not caught up yet");
+
+    second.quiescent_point();
+    assert_eq!(pool.free_blocks(), 2);
+
+    // Reused blocks come from the pool, not the system allocator
+    first.alloc(3u64);
+    second.alloc(4u64);
+    assert_eq!(pool.blocks_created(), 2);
+    assert_eq!(pool.free_blocks(), 0);
+}
+
+#[test]
+fn dropping_an_arena_counts_as_a_quiescent_point() {
+    let pool = ArenaPool::new(64);
+    let mut first = pool.arena();
+    let second = pool.arena();
+    first.alloc(1u64);
+    second.alloc(2u64);
+
+    pool.request_reset();
+    first.quiescent_point();
+    drop(second);
+    assert_eq!(pool.free_blocks(), 2);
+}
+
+#[test]
+fn threads_allocate_from_their_own_arenas() {
+    let pool = ArenaPool::new(4 * 1024);
+
+    std::thread::scope(|scope| {
+        for thread in 0..4u64 {
+            let pool = &pool;
+            scope.spawn(move || {
+                let mut arena = pool.arena();
+                for round in 0..3 {
+                    let values: Vec<&mut u64> =
+                        (0..1_000).map(|i| arena.alloc(thread * i)).collect();
+                    assert!(values
+                        .iter()
+                        .enumerate()
+                        .all(|(i, value)| **value == thread * i as u64));
+                    drop(values);
+                    if thread == 0 && round == 1 {
+                        pool.request_reset();
+                    }
+                    arena.quiescent_point();
+                }
+            });
+        }
+    });
+
+    assert!(pool.blocks_created() > 0);
+}